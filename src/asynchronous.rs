@@ -0,0 +1,124 @@
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::task::{self, JoinHandle};
+
+use std::future::Future;
+use std::panic;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use {Bincode, Codec, ErrorKind, Prefix, Result};
+
+/// A prefix whose functions run on the blocking thread pool of tokio, so they do not block the
+/// thread of an async task.
+///
+/// The functions take owned keys and values, since they are moved to another thread, and return
+/// a `Blocking` future that resolves to the result. Requires the `async` feature and must be used
+/// from within a tokio runtime.
+///
+/// Created with `Prefix::into_async`.
+///
+/// # Examples
+/// ```
+/// extern crate tokio;
+/// # extern crate rocksbin;
+///
+/// # fn main() {
+/// # let db = rocksbin::DB::open("db_dir_async").unwrap();
+/// let heights = db.prefix::<String, u64>(b"heights").unwrap().into_async();
+/// let runtime = tokio::runtime::Runtime::new().unwrap();
+///
+/// runtime.block_on(heights.insert("John".to_string(), 175)).unwrap();
+///
+/// assert_eq!(runtime.block_on(heights.get("John".to_string())).unwrap(), Some(175));
+///
+/// # drop(heights);
+/// # drop(db);
+/// # std::fs::remove_dir_all("db_dir_async").unwrap();
+/// # }
+/// ```
+pub struct AsyncPrefix<K, V, C = Bincode> {
+    prefix: Arc<Prefix<K, V, C>>,
+}
+
+impl<K, V, C> Clone for AsyncPrefix<K, V, C> {
+    fn clone(&self) -> AsyncPrefix<K, V, C> {
+        AsyncPrefix {
+            prefix: self.prefix.clone(),
+        }
+    }
+}
+
+impl<K, V, C> AsyncPrefix<K, V, C>
+where
+    K: Serialize + DeserializeOwned + Send + Sync + 'static,
+    V: Serialize + DeserializeOwned + Send + Sync + 'static,
+    C: Codec + Send + Sync + 'static,
+{
+    pub(crate) fn new(prefix: Prefix<K, V, C>) -> AsyncPrefix<K, V, C> {
+        AsyncPrefix {
+            prefix: Arc::new(prefix),
+        }
+    }
+
+    /// The prefix this runs its functions on.
+    pub fn prefix(&self) -> &Prefix<K, V, C> {
+        &self.prefix
+    }
+
+    fn spawn<T, F>(&self, f: F) -> Blocking<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Prefix<K, V, C>) -> Result<T> + Send + 'static,
+    {
+        let prefix = self.prefix.clone();
+        Blocking {
+            handle: task::spawn_blocking(move || f(&prefix)),
+        }
+    }
+
+    /// Returns the value coresponing to the key, see `Prefix::get`.
+    pub fn get(&self, key: K) -> Blocking<Option<V>> {
+        self.spawn(move |prefix| prefix.get(&key))
+    }
+
+    /// Whether there is a value coresponing to the key, see `Prefix::contains_key`.
+    pub fn contains_key(&self, key: K) -> Blocking<bool> {
+        self.spawn(move |prefix| prefix.contains_key(&key))
+    }
+
+    /// Insert a key-value pair, see `Prefix::insert`.
+    pub fn insert(&self, key: K, value: V) -> Blocking<()> {
+        self.spawn(move |prefix| prefix.insert(&key, &value))
+    }
+
+    /// Remove a key-value pair, see `Prefix::remove`.
+    pub fn remove(&self, key: K) -> Blocking<()> {
+        self.spawn(move |prefix| prefix.remove(&key))
+    }
+}
+
+/// A function of `AsyncPrefix` running on the blocking thread pool.
+///
+/// Resolves to the result of the function. If the function panics the panic is resumed in the
+/// task awaiting the future.
+pub struct Blocking<T> {
+    handle: JoinHandle<Result<T>>,
+}
+
+impl<T> Future for Blocking<T> {
+    type Output = Result<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<T>> {
+        match Pin::new(&mut self.handle).poll(cx) {
+            Poll::Ready(Ok(result)) => Poll::Ready(result),
+            Poll::Ready(Err(e)) => {
+                if e.is_panic() {
+                    panic::resume_unwind(e.into_panic())
+                }
+                Poll::Ready(Err(Box::new(ErrorKind::Cancelled)))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
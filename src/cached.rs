@@ -0,0 +1,126 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use {Bincode, Codec, Prefix, Result};
+
+/// A prefix with an in-process cache of deserialized values in front of it.
+///
+/// Values read through the cache are kept in memory, so repeated reads of the same key do not
+/// have to go to rocksdb or deserialize the value again. Writes through `CachedPrefix` keep the
+/// cache up to date, but writes made through other handles to the same prefix are not seen
+/// until `clear_cache` is called.
+///
+/// The cache is locked while a value is read from or written to the database, so a read racing
+/// with a write through the same cache never leaves an outdated value cached.
+///
+/// Created with `Prefix::cached`.
+#[derive(Clone)]
+pub struct CachedPrefix<K, V, C = Bincode> {
+    prefix: Prefix<K, V, C>,
+    cache: Arc<Mutex<HashMap<Vec<u8>, V>>>,
+}
+
+impl<K, V, C> CachedPrefix<K, V, C>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned + Clone,
+    C: Codec,
+{
+    pub(crate) fn new(prefix: Prefix<K, V, C>) -> CachedPrefix<K, V, C> {
+        CachedPrefix {
+            prefix,
+            cache: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Returns the value coresponing to the key, from the cache if possible.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::get`
+    pub fn get<Q>(&self, key: &Q) -> Result<Option<V>>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let key_buf = self.prefix.key_buf(key)?;
+        let mut cache = self.cache.lock().unwrap();
+        if let Some(value) = cache.get(&key_buf) {
+            return Ok(Some(value.clone()));
+        }
+
+        let value = self.prefix.get(key)?;
+        if let Some(ref value) = value {
+            cache.insert(key_buf, value.clone());
+        }
+        Ok(value)
+    }
+
+    /// Returns the value coresponing to the key. If there is no such value, it is computed with
+    /// `compute` and inserted into both the database and the cache.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::get` and `Prefix::insert`
+    ///
+    /// # Examples
+    /// ```
+    /// # let db = rocksbin::DB::open("db_dir_cached").unwrap();
+    /// let squares = db.prefix::<u64, u64>(b"squares").unwrap().cached();
+    ///
+    /// assert_eq!(squares.get_or_compute(&12, || 12 * 12).unwrap(), 144);
+    /// assert_eq!(squares.get_or_compute(&12, || unreachable!()).unwrap(), 144);
+    ///
+    /// # drop(squares);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_cached").unwrap();
+    /// ```
+    pub fn get_or_compute<Q, F: FnOnce() -> V>(&self, key: &Q, compute: F) -> Result<V>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        match self.get(key)? {
+            Some(value) => Ok(value),
+            None => {
+                let value = compute();
+                self.insert(key, &value)?;
+                Ok(value)
+            }
+        }
+    }
+
+    /// Insert a key-value pair into both the database and the cache.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::insert`
+    pub fn insert<Q>(&self, key: &Q, value: &V) -> Result<()>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let key_buf = self.prefix.key_buf(key)?;
+        let mut cache = self.cache.lock().unwrap();
+        self.prefix.insert(key, value)?;
+        cache.insert(key_buf, value.clone());
+        Ok(())
+    }
+
+    /// Removes a key-value pair from both the database and the cache.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::remove`
+    pub fn remove<Q>(&self, key: &Q) -> Result<()>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let key_buf = self.prefix.key_buf(key)?;
+        let mut cache = self.cache.lock().unwrap();
+        self.prefix.remove(key)?;
+        cache.remove(&key_buf);
+        Ok(())
+    }
+
+    /// Drop every cached value, following reads go to the database again.
+    pub fn clear_cache(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+}
@@ -0,0 +1,358 @@
+//! Canonical serialization of values.
+//!
+//! bincode serializes maps in iteration order, so two equal `HashMap`s can serialize to
+//! different bytes. The serializer in this module produces exactly the same format as
+//! `bincode::serialize`, except that map entries are sorted by their serialized key. The output
+//! can therefore be read back with `bincode::deserialize`.
+//!
+//! Only maps are reordered. Sets like `HashSet` serialize as sequences, which can not be told
+//! apart from ordered sequences like `Vec`, so use `BTreeSet` if you need a deterministic set.
+
+use bincode;
+use serde::ser::{self, Serialize};
+
+/// Serialize `value` like `bincode::serialize` but with map entries sorted by key.
+///
+/// # Examples
+/// ```
+/// use std::collections::HashMap;
+///
+/// let a: HashMap<u64, u64> = (0..100).map(|i| (i, i * 2)).collect();
+/// let b: HashMap<u64, u64> = (0..100).rev().map(|i| (i, i * 2)).collect();
+///
+/// assert_eq!(
+///     rocksbin::canonical::serialize(&a).unwrap(),
+///     rocksbin::canonical::serialize(&b).unwrap()
+/// );
+/// ```
+pub fn serialize<T: Serialize + ?Sized>(value: &T) -> bincode::Result<Vec<u8>> {
+    let mut serializer = Canonical { out: Vec::new() };
+    value.serialize(&mut serializer)?;
+    Ok(serializer.out)
+}
+
+struct Canonical {
+    out: Vec<u8>,
+}
+
+impl Canonical {
+    fn write_len(&mut self, len: usize) {
+        self.out.extend_from_slice(&(len as u64).to_le_bytes());
+    }
+
+    fn write_variant(&mut self, variant_index: u32) {
+        self.out.extend_from_slice(&variant_index.to_le_bytes());
+    }
+}
+
+/// Serializer for every compound type except maps, the elements are written in order.
+struct Compound<'a> {
+    ser: &'a mut Canonical,
+}
+
+/// Serializer for maps, the entries are buffered and sorted when the map ends.
+struct MapCompound<'a> {
+    ser: &'a mut Canonical,
+    entries: Vec<(Vec<u8>, Vec<u8>)>,
+    key: Option<Vec<u8>>,
+}
+
+impl<'a> ser::Serializer for &'a mut Canonical {
+    type Ok = ();
+    type Error = bincode::Error;
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = Compound<'a>;
+    type SerializeMap = MapCompound<'a>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = Compound<'a>;
+
+    fn serialize_bool(self, v: bool) -> bincode::Result<()> {
+        self.out.push(v as u8);
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> bincode::Result<()> {
+        self.out.extend_from_slice(&v.to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_i16(self, v: i16) -> bincode::Result<()> {
+        self.out.extend_from_slice(&v.to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_i32(self, v: i32) -> bincode::Result<()> {
+        self.out.extend_from_slice(&v.to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_i64(self, v: i64) -> bincode::Result<()> {
+        self.out.extend_from_slice(&v.to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_i128(self, v: i128) -> bincode::Result<()> {
+        self.out.extend_from_slice(&v.to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_u8(self, v: u8) -> bincode::Result<()> {
+        self.out.push(v);
+        Ok(())
+    }
+
+    fn serialize_u16(self, v: u16) -> bincode::Result<()> {
+        self.out.extend_from_slice(&v.to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_u32(self, v: u32) -> bincode::Result<()> {
+        self.out.extend_from_slice(&v.to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_u64(self, v: u64) -> bincode::Result<()> {
+        self.out.extend_from_slice(&v.to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_u128(self, v: u128) -> bincode::Result<()> {
+        self.out.extend_from_slice(&v.to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_f32(self, v: f32) -> bincode::Result<()> {
+        self.out.extend_from_slice(&v.to_bits().to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_f64(self, v: f64) -> bincode::Result<()> {
+        self.out.extend_from_slice(&v.to_bits().to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> bincode::Result<()> {
+        // bincode writes chars as plain utf8 without a length
+        let mut buf = [0; 4];
+        self.out.extend_from_slice(v.encode_utf8(&mut buf).as_bytes());
+        Ok(())
+    }
+
+    fn serialize_str(self, v: &str) -> bincode::Result<()> {
+        self.serialize_bytes(v.as_bytes())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> bincode::Result<()> {
+        self.write_len(v.len());
+        self.out.extend_from_slice(v);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> bincode::Result<()> {
+        self.out.push(0);
+        Ok(())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> bincode::Result<()> {
+        self.out.push(1);
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> bincode::Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> bincode::Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+    ) -> bincode::Result<()> {
+        self.write_variant(variant_index);
+        Ok(())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> bincode::Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> bincode::Result<()> {
+        self.write_variant(variant_index);
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> bincode::Result<Compound<'a>> {
+        let len = len.ok_or(bincode::ErrorKind::SequenceMustHaveLength)?;
+        self.write_len(len);
+        Ok(Compound { ser: self })
+    }
+
+    fn serialize_tuple(self, _len: usize) -> bincode::Result<Compound<'a>> {
+        Ok(Compound { ser: self })
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> bincode::Result<Compound<'a>> {
+        Ok(Compound { ser: self })
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> bincode::Result<Compound<'a>> {
+        self.write_variant(variant_index);
+        Ok(Compound { ser: self })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> bincode::Result<MapCompound<'a>> {
+        Ok(MapCompound {
+            ser: self,
+            entries: Vec::new(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> bincode::Result<Compound<'a>> {
+        Ok(Compound { ser: self })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> bincode::Result<Compound<'a>> {
+        self.write_variant(variant_index);
+        Ok(Compound { ser: self })
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+impl<'a> ser::SerializeSeq for Compound<'a> {
+    type Ok = ();
+    type Error = bincode::Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> bincode::Result<()> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> bincode::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeTuple for Compound<'a> {
+    type Ok = ();
+    type Error = bincode::Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> bincode::Result<()> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> bincode::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeTupleStruct for Compound<'a> {
+    type Ok = ();
+    type Error = bincode::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> bincode::Result<()> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> bincode::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeTupleVariant for Compound<'a> {
+    type Ok = ();
+    type Error = bincode::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> bincode::Result<()> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> bincode::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeStruct for Compound<'a> {
+    type Ok = ();
+    type Error = bincode::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, _key: &'static str, value: &T) -> bincode::Result<()> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> bincode::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeStructVariant for Compound<'a> {
+    type Ok = ();
+    type Error = bincode::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, _key: &'static str, value: &T) -> bincode::Result<()> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> bincode::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeMap for MapCompound<'a> {
+    type Ok = ();
+    type Error = bincode::Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> bincode::Result<()> {
+        self.key = Some(serialize(key)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> bincode::Result<()> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| <bincode::Error as ser::Error>::custom("map value serialized before its key"))?;
+        self.entries.push((key, serialize(value)?));
+        Ok(())
+    }
+
+    fn end(mut self) -> bincode::Result<()> {
+        self.entries.sort();
+        self.ser.write_len(self.entries.len());
+        for (key, value) in self.entries {
+            self.ser.out.extend_from_slice(&key);
+            self.ser.out.extend_from_slice(&value);
+        }
+        Ok(())
+    }
+}
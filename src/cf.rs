@@ -0,0 +1,154 @@
+use rocksdb;
+use serde::{de::DeserializeOwned, Serialize};
+
+use std::borrow::Borrow;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use {decode_value, encode_key, Bincode, BincodeConfig, Codec, Iter, Keys, RawIter, Result, SizeLimits, Source, Values};
+
+/// A prefix backed by its own rocksdb column family instead of a key prefix.
+///
+/// Every column family has its own memtables and SST files, so a huge prefix does not slow down
+/// compaction of a tiny one, and iteration never has to skip entries of other prefixes.
+///
+/// The options of the column family are set when it is created, see
+/// `DB::cf_prefix_with_options`. They can not be changed at runtime like with `DB::set_options`,
+/// as the rocksdb bindings only support that for the default column family.
+///
+/// Created with `DB::cf_prefix`.
+#[derive(Clone)]
+pub struct CfPrefix<K, V> {
+    db: Arc<rocksdb::DB>,
+    name: String,
+    config: BincodeConfig,
+    limits: SizeLimits,
+    _k: PhantomData<K>,
+    _v: PhantomData<V>,
+}
+
+impl<K: Serialize + DeserializeOwned, V: Serialize + DeserializeOwned> CfPrefix<K, V> {
+    pub(crate) fn new(db: Arc<rocksdb::DB>, name: &str, config: BincodeConfig, limits: SizeLimits) -> CfPrefix<K, V> {
+        CfPrefix {
+            db,
+            name: name.to_string(),
+            config,
+            limits,
+            _k: PhantomData,
+            _v: PhantomData,
+        }
+    }
+
+    fn cf(&self) -> rocksdb::ColumnFamily {
+        // Column families are created by `DB::cf_prefix` and never dropped
+        self.db.cf_handle(&self.name).expect("column family of CfPrefix is missing")
+    }
+
+    fn key_buf<Q: Serialize + ?Sized>(&self, key: &Q) -> Result<Vec<u8>> {
+        let size = self.config.serialized_size(&key)? as usize;
+        self.limits.check_key(size)?;
+
+        let mut key_buf = Vec::with_capacity(size);
+        encode_key(&mut key_buf, key, self.config)?;
+        Ok(key_buf)
+    }
+
+    fn raw_iter(&self) -> RawIter {
+        RawIter::new(Source::Cf(self.db.clone(), self.cf()), Vec::new(), &[], None)
+    }
+
+    /// The name of the column family.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the value coresponing to the key.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::get`
+    pub fn get<Q>(&self, key: &Q) -> Result<Option<V>>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let key_buf = self.key_buf(key)?;
+        match self.db.get_cf(self.cf(), &key_buf)? {
+            Some(data) => Ok(Some(decode_value::<Bincode, _>(&key_buf, &data, false, self.config)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Whether there is a value coresponing to the key.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::contains_key`
+    pub fn contains_key<Q>(&self, key: &Q) -> Result<bool>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        Ok(self.db.get_cf(self.cf(), &self.key_buf(key)?)?.is_some())
+    }
+
+    /// Insert a key-value pair. If the key already exists the value is updated.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::insert`
+    pub fn insert<Q>(&self, key: &Q, value: &V) -> Result<()>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let key_buf = self.key_buf(key)?;
+        let value_buf = Bincode::serialize_with(value, self.config)?;
+        self.limits.check_value(value_buf.len())?;
+
+        self.db.put_cf(self.cf(), &key_buf, &value_buf)?;
+        Ok(())
+    }
+
+    /// Remove a key-value pair.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::remove`
+    pub fn remove<Q>(&self, key: &Q) -> Result<()>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        self.db.delete_cf(self.cf(), &self.key_buf(key)?)?;
+        Ok(())
+    }
+
+    /// An iterator visiting all key-value pairs of the column family.
+    /// The iterator type is `Result<(K, V), Error>`
+    pub fn iter(&self) -> Iter<K, V> {
+        Iter {
+            raw: self.raw_iter(),
+            checksum: false,
+            values: self.config,
+            keys: self.config,
+            _k: PhantomData,
+            _v: PhantomData,
+            _c: PhantomData,
+        }
+    }
+
+    /// An iterator visiting all keys of the column family.
+    /// The iterator type is `Result<K, Error>`
+    pub fn keys(&self) -> Keys<K> {
+        Keys {
+            raw: self.raw_iter(),
+            keys: self.config,
+            _k: PhantomData,
+        }
+    }
+
+    /// An iterator visiting all values of the column family.
+    /// The iterator type is `Result<V, Error>`
+    pub fn values(&self) -> Values<V> {
+        Values {
+            raw: self.raw_iter(),
+            checksum: false,
+            values: self.config,
+            _v: PhantomData,
+            _c: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use bincode::{self, Options};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use Result;
+
+/// A serialization format for the values of a prefix, see `DB::prefix_with_codec`.
+///
+/// Keys are always serialized with bincode, as their byte representation determines the order
+/// of iteration.
+///
+/// # Examples
+/// ```
+/// extern crate serde;
+/// extern crate serde_json;
+/// # extern crate rocksbin;
+///
+/// use rocksbin::{Codec, ErrorKind};
+/// use serde::{de::DeserializeOwned, Serialize};
+///
+/// #[derive(Clone)]
+/// struct Json;
+///
+/// impl Codec for Json {
+///     fn serialize<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, rocksbin::Error> {
+///         serde_json::to_vec(value).map_err(|e| Box::new(ErrorKind::Codec(Box::new(e))))
+///     }
+///
+///     fn deserialize<T: DeserializeOwned>(data: &[u8]) -> Result<T, rocksbin::Error> {
+///         serde_json::from_slice(data).map_err(|e| Box::new(ErrorKind::Codec(Box::new(e))))
+///     }
+/// }
+///
+/// # fn main() {
+/// # let db = rocksbin::DB::open("db_dir_codec").unwrap();
+/// let heights = db.prefix_with_codec::<String, u64, Json>(b"heights").unwrap();
+///
+/// heights.insert("John", &175).unwrap();
+/// assert_eq!(heights.get("John").unwrap(), Some(175));
+///
+/// # drop(heights);
+/// # drop(db);
+/// # std::fs::remove_dir_all("db_dir_codec").unwrap();
+/// # }
+/// ```
+pub trait Codec {
+    /// Serialize a value.
+    fn serialize<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>>;
+
+    /// Deserialize a value serialized by `Codec::serialize`.
+    fn deserialize<T: DeserializeOwned>(data: &[u8]) -> Result<T>;
+
+    /// Serialize a value of a database opened with the bincode configuration `config`, see
+    /// `DB::open_with_config`. Codecs that do not use bincode can ignore it, which is the default.
+    fn serialize_with<T: Serialize + ?Sized>(value: &T, _config: BincodeConfig) -> Result<Vec<u8>> {
+        Self::serialize(value)
+    }
+
+    /// Deserialize a value serialized by `Codec::serialize_with`.
+    fn deserialize_with<T: DeserializeOwned>(data: &[u8], _config: BincodeConfig) -> Result<T> {
+        Self::deserialize(data)
+    }
+}
+
+/// The default codec, serializing values with `bincode::serialize`.
+///
+/// Bincode does not store field names or types, so values must be read with the type they were
+/// written with. Reading a value as a type with more fields fails, and so does reading it as a
+/// type with fewer fields, as the trailing bytes are rejected instead of ignored. Either way the
+/// error is `ErrorKind::Bincode`. To change the type of a prefix, read old values with
+/// `Prefix::get_either` or migrate them to a new prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Bincode;
+
+impl Codec for Bincode {
+    fn serialize<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
+        Bincode::serialize_with(value, BincodeConfig::default())
+    }
+
+    fn deserialize<T: DeserializeOwned>(data: &[u8]) -> Result<T> {
+        Bincode::deserialize_with(data, BincodeConfig::default())
+    }
+
+    fn serialize_with<T: Serialize + ?Sized>(value: &T, config: BincodeConfig) -> Result<Vec<u8>> {
+        Ok(config.serialize(value)?)
+    }
+
+    fn deserialize_with<T: DeserializeOwned>(data: &[u8], config: BincodeConfig) -> Result<T> {
+        Ok(config.deserialize_value(data)?)
+    }
+}
+
+/// The bincode configuration of a database, see `DB::open_with_config`.
+///
+/// The default is the configuration of `bincode::serialize`, which stores integers with their
+/// full width in little endian.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BincodeConfig {
+    /// How integers are encoded.
+    pub int_encoding: IntEncoding,
+    /// The byte order of integers.
+    pub endian: Endian,
+}
+
+/// How bincode encodes integers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IntEncoding {
+    /// Every integer uses as many bytes as its type.
+    #[default]
+    Fixint,
+    /// Small integers use fewer bytes, see `bincode::Options::with_varint_encoding`. Lengths of
+    /// strings and sequences are integers as well, so they shrink too.
+    Varint,
+}
+
+/// The byte order bincode stores integers in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Endian {
+    #[default]
+    Little,
+    Big,
+}
+
+/// The configuration of the keys of `Prefix::ordered` prefixes.
+pub(crate) const ORDERED: BincodeConfig = BincodeConfig {
+    int_encoding: IntEncoding::Fixint,
+    endian: Endian::Big,
+};
+
+/// Evaluate `$body` with `$opts` bound to the bincode options of `$config`.
+macro_rules! with_options {
+    ($config:expr, |$opts:ident| $body:expr) => {{
+        let opts = bincode::DefaultOptions::new();
+        match ($config.int_encoding, $config.endian) {
+            (IntEncoding::Fixint, Endian::Little) => {
+                let $opts = opts.with_fixint_encoding().with_little_endian();
+                $body
+            }
+            (IntEncoding::Fixint, Endian::Big) => {
+                let $opts = opts.with_fixint_encoding().with_big_endian();
+                $body
+            }
+            (IntEncoding::Varint, Endian::Little) => {
+                let $opts = opts.with_varint_encoding().with_little_endian();
+                $body
+            }
+            (IntEncoding::Varint, Endian::Big) => {
+                let $opts = opts.with_varint_encoding().with_big_endian();
+                $body
+            }
+        }
+    }};
+}
+
+impl BincodeConfig {
+    /// The configuration as it is stored in a database.
+    pub(crate) fn to_bytes(self) -> [u8; 2] {
+        let int_encoding = match self.int_encoding {
+            IntEncoding::Fixint => 0,
+            IntEncoding::Varint => 1,
+        };
+        let endian = match self.endian {
+            Endian::Little => 0,
+            Endian::Big => 1,
+        };
+        [int_encoding, endian]
+    }
+
+    /// Read a configuration stored by `BincodeConfig::to_bytes`.
+    pub(crate) fn from_bytes(data: &[u8]) -> Option<BincodeConfig> {
+        let int_encoding = match *data.first()? {
+            0 => IntEncoding::Fixint,
+            1 => IntEncoding::Varint,
+            _ => return None,
+        };
+        let endian = match *data.get(1)? {
+            0 => Endian::Little,
+            1 => Endian::Big,
+            _ => return None,
+        };
+        Some(BincodeConfig { int_encoding, endian })
+    }
+
+    pub(crate) fn serialize<T: Serialize + ?Sized>(self, value: &T) -> bincode::Result<Vec<u8>> {
+        with_options!(self, |opts| opts.serialize(value))
+    }
+
+    pub(crate) fn serialize_into<T: Serialize + ?Sized>(self, buf: &mut Vec<u8>, value: &T) -> bincode::Result<()> {
+        with_options!(self, |opts| opts.serialize_into(buf, value))
+    }
+
+    pub(crate) fn serialized_size<T: Serialize + ?Sized>(self, value: &T) -> bincode::Result<u64> {
+        with_options!(self, |opts| opts.serialized_size(value))
+    }
+
+    /// Deserialize a key, or the start of a value, which may be followed by other bytes like the
+    /// rest of a tuple, see `Prefix::count_distinct_key_prefix`.
+    pub(crate) fn deserialize_key<'a, T: Deserialize<'a>>(self, data: &'a [u8]) -> bincode::Result<T> {
+        with_options!(self, |opts| opts.allow_trailing_bytes().deserialize(data))
+    }
+
+    /// Deserialize a value, rejecting trailing bytes.
+    pub(crate) fn deserialize_value<'a, T: Deserialize<'a>>(self, data: &'a [u8]) -> bincode::Result<T> {
+        with_options!(self, |opts| opts.deserialize(data))
+    }
+}
@@ -0,0 +1,159 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+use std::mem;
+
+use {Bincode, Codec, Prefix, Result};
+
+/// A key of a prefix, which is either occupied or vacant, like the entries of `HashMap`.
+///
+/// The entry holds the value that was read by `Prefix::entry`, and writes it back under the key
+/// that was serialized then. Writes made by others in the meantime may be overwritten, use
+/// [`Prefix::update_retry`] or [`Prefix::compare_and_swap`] if the read and write have to be
+/// atomic.
+///
+/// # Examples
+/// ```
+/// # let db = rocksbin::DB::open("db_dir_entry").unwrap();
+/// let counts = db.prefix::<String, u64>(b"counts").unwrap();
+///
+/// counts.entry("apple").unwrap().and_modify(|count| *count += 1).unwrap().or_insert(1).unwrap();
+/// counts.entry("apple").unwrap().and_modify(|count| *count += 1).unwrap().or_insert(1).unwrap();
+///
+/// assert_eq!(counts.get("apple").unwrap(), Some(2));
+///
+/// # drop(counts);
+/// # drop(db);
+/// # std::fs::remove_dir_all("db_dir_entry").unwrap();
+/// ```
+pub enum Entry<'a, K: 'a, V: 'a, C: 'a = Bincode> {
+    Occupied(OccupiedEntry<'a, K, V, C>),
+    Vacant(VacantEntry<'a, K, V, C>),
+}
+
+impl<'a, K, V, C> Entry<'a, K, V, C>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+    C: Codec,
+{
+    /// Returns the value, inserting `default` if the entry is vacant.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::insert`
+    pub fn or_insert(self, default: V) -> Result<V> {
+        match self {
+            Entry::Occupied(entry) => Ok(entry.into_value()),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    /// Returns the value, inserting the result of `default` if the entry is vacant.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::insert`
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> Result<V> {
+        match self {
+            Entry::Occupied(entry) => Ok(entry.into_value()),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Returns the value, inserting `V::default()` if the entry is vacant.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::insert`
+    pub fn or_default(self) -> Result<V>
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    /// Apply `f` to the value and write it back if the entry is occupied.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::insert`
+    pub fn and_modify<F: FnOnce(&mut V)>(self, f: F) -> Result<Entry<'a, K, V, C>> {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(&mut entry.value);
+                entry.write()?;
+                Ok(Entry::Occupied(entry))
+            }
+            Entry::Vacant(entry) => Ok(Entry::Vacant(entry)),
+        }
+    }
+}
+
+/// An entry of a key that has a value, see `Entry`.
+pub struct OccupiedEntry<'a, K: 'a, V: 'a, C: 'a = Bincode> {
+    prefix: &'a Prefix<K, V, C>,
+    key_buf: Vec<u8>,
+    value: V,
+}
+
+impl<'a, K, V, C> OccupiedEntry<'a, K, V, C>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+    C: Codec,
+{
+    pub(crate) fn new(prefix: &'a Prefix<K, V, C>, key_buf: Vec<u8>, value: V) -> OccupiedEntry<'a, K, V, C> {
+        OccupiedEntry { prefix, key_buf, value }
+    }
+
+    fn write(&self) -> Result<()> {
+        let value_buf = self.prefix.value_buf(&self.value)?;
+        self.prefix.db.put(&self.key_buf, &value_buf)?;
+        Ok(())
+    }
+
+    /// The value of the entry.
+    pub fn get(&self) -> &V {
+        &self.value
+    }
+
+    /// Take the value of the entry.
+    pub fn into_value(self) -> V {
+        self.value
+    }
+
+    /// Replace the value of the entry with `value`, returning the old value.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::insert`
+    pub fn insert(&mut self, value: V) -> Result<V> {
+        let old = mem::replace(&mut self.value, value);
+        self.write()?;
+        Ok(old)
+    }
+
+    /// Remove the key, returning its value.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::remove`
+    pub fn remove(self) -> Result<V> {
+        self.prefix.db.delete(&self.key_buf)?;
+        Ok(self.value)
+    }
+}
+
+/// An entry of a key that has no value, see `Entry`.
+pub struct VacantEntry<'a, K: 'a, V: 'a, C: 'a = Bincode> {
+    prefix: &'a Prefix<K, V, C>,
+    key_buf: Vec<u8>,
+}
+
+impl<'a, K, V, C> VacantEntry<'a, K, V, C>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+    C: Codec,
+{
+    pub(crate) fn new(prefix: &'a Prefix<K, V, C>, key_buf: Vec<u8>) -> VacantEntry<'a, K, V, C> {
+        VacantEntry { prefix, key_buf }
+    }
+
+    /// Insert `value` under the key, returning it.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::insert`
+    pub fn insert(self, value: V) -> Result<V> {
+        let value_buf = self.prefix.value_buf(&value)?;
+        self.prefix.db.put(&self.key_buf, &value_buf)?;
+        Ok(value)
+    }
+}
@@ -0,0 +1,141 @@
+use rocksdb;
+use serde::{de::DeserializeOwned, Serialize};
+
+use std::borrow::Borrow;
+use std::sync::Arc;
+
+use {Bincode, Codec, Prefix, Result};
+
+type ExtractFn<K, V, I> = Arc<dyn Fn(&K, &V) -> I + Send + Sync>;
+
+/// A prefix together with a secondary index that is kept up to date on every write.
+///
+/// The index maps an index key, computed from each key-value pair by the extractor, back to the
+/// key of the pair, e.g., from the email of a user to its id. Index keys are unique: inserting a
+/// pair whose index key is already used by another key makes the index point at the new key.
+///
+/// Writes through `Index` update the prefix and the index in one atomic batch, and are atomic
+/// with respect to each other and functions like [`Prefix::update_retry`] and [`Prefix::swap`].
+/// Writes made directly to the prefix are not indexed.
+///
+/// Created with `Prefix::indexed`.
+///
+/// # Examples
+/// ```
+/// # let db = rocksbin::DB::open("db_dir_index").unwrap();
+/// let users = db.prefix::<u64, (String, String)>(b"users").unwrap();
+/// let emails = db.prefix::<String, u64>(b"users_by_email").unwrap();
+/// let users = users.indexed(emails, |_, user| user.1.clone()).unwrap();
+///
+/// users.insert(&1, &("John".to_string(), "john@example.com".to_string())).unwrap();
+///
+/// assert_eq!(users.get("john@example.com").unwrap(), Some(1));
+///
+/// users.remove(&1).unwrap();
+/// assert_eq!(users.get("john@example.com").unwrap(), None);
+///
+/// # drop(users);
+/// # drop(db);
+/// # std::fs::remove_dir_all("db_dir_index").unwrap();
+/// ```
+#[derive(Clone)]
+pub struct Index<K, V, I, C = Bincode> {
+    prefix: Prefix<K, V, C>,
+    index: Prefix<I, K>,
+    extract: ExtractFn<K, V, I>,
+}
+
+impl<K, V, I, C> Index<K, V, I, C>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+    I: Serialize + DeserializeOwned,
+    C: Codec,
+{
+    pub(crate) fn new<F>(prefix: Prefix<K, V, C>, index: Prefix<I, K>, extract: F) -> Index<K, V, I, C>
+    where
+        F: Fn(&K, &V) -> I + Send + Sync + 'static,
+    {
+        Index {
+            prefix,
+            index,
+            extract: Arc::new(extract),
+        }
+    }
+
+    /// The indexed prefix, for reading.
+    pub fn prefix(&self) -> &Prefix<K, V, C> {
+        &self.prefix
+    }
+
+    /// Returns the key whose pair has the index key `index_key`.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::get`
+    pub fn get<Q>(&self, index_key: &Q) -> Result<Option<K>>
+    where
+        I: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        self.index.get(index_key)
+    }
+
+    /// Insert a key-value pair into the prefix and index it, removing the index entry of the
+    /// value it replaces.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::get` and `Prefix::insert`
+    pub fn insert(&self, key: &K, value: &V) -> Result<()> {
+        let key_buf = self.prefix.key_buf(key)?;
+        let value_buf = self.prefix.value_buf(value)?;
+        let index_key_buf = self.index.key_buf(&(self.extract)(key, value))?;
+        let index_value_buf = self.index.value_buf(key)?;
+
+        let _guard = self.prefix.update_lock.lock().unwrap();
+        let mut batch = rocksdb::WriteBatch::default();
+
+        if let Some(old) = self.prefix.get_buf(&key_buf)? {
+            self.remove_index_entry(&mut batch, key, &old, &index_value_buf)?;
+        }
+        batch.put(&key_buf, &value_buf)?;
+        batch.put(&index_key_buf, &index_value_buf)?;
+
+        self.prefix.db.write(batch)?;
+        Ok(())
+    }
+
+    /// Remove a key-value pair from the prefix together with its index entry.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::get` and `Prefix::remove`
+    pub fn remove(&self, key: &K) -> Result<()> {
+        let key_buf = self.prefix.key_buf(key)?;
+        let index_value_buf = self.index.value_buf(key)?;
+
+        let _guard = self.prefix.update_lock.lock().unwrap();
+        let mut batch = rocksdb::WriteBatch::default();
+
+        if let Some(old) = self.prefix.get_buf(&key_buf)? {
+            self.remove_index_entry(&mut batch, key, &old, &index_value_buf)?;
+        }
+        batch.delete(&key_buf)?;
+
+        self.prefix.db.write(batch)?;
+        Ok(())
+    }
+
+    /// Remove the index entry of `key` and `value` in `batch`, unless it already points at
+    /// another key.
+    fn remove_index_entry(
+        &self,
+        batch: &mut rocksdb::WriteBatch,
+        key: &K,
+        value: &V,
+        index_value_buf: &[u8],
+    ) -> Result<()> {
+        let index_key_buf = self.index.key_buf(&(self.extract)(key, value))?;
+        if let Some(data) = self.index.db.get(&index_key_buf)? {
+            if &data[..] == index_value_buf {
+                batch.delete(&index_key_buf)?;
+            }
+        }
+        Ok(())
+    }
+}
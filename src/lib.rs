@@ -39,25 +39,139 @@
 extern crate bincode;
 extern crate rocksdb;
 extern crate serde;
+#[cfg(feature = "json")]
+extern crate serde_json;
+#[cfg(feature = "async")]
+extern crate tokio;
 
-use serde::{de::DeserializeOwned, Serialize, ser::SerializeSeq, Serializer, Deserializer, de::Visitor, de::SeqAccess, de::value::SeqDeserializer, de::value::U8Deserializer};
+use serde::{de::DeserializeOwned, Deserialize, Serialize, ser::SerializeSeq, Serializer, Deserializer, de::Visitor, de::SeqAccess, de::MapAccess, de::value::SeqDeserializer, de::value::U8Deserializer};
 
+use std::any::type_name;
+use std::ascii;
 use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use std::error;
 use std::fmt;
+use std::fs;
+use std::hash::Hash;
+use std::io;
+use std::iter::FusedIterator;
 use std::marker::PhantomData;
+use std::mem;
+use std::ops::{Bound, RangeBounds};
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::{mpsc, Arc, Mutex, RwLock};
+use std::thread;
+
+#[cfg(feature = "async")]
+mod asynchronous;
+mod cached;
+mod cf;
+pub mod canonical;
+mod codec;
+mod entry;
+mod index;
+mod merge;
+mod multi_get;
+mod raw;
+mod snapshot;
+mod transaction;
+mod ttl;
+
+#[cfg(feature = "async")]
+pub use asynchronous::{AsyncPrefix, Blocking};
+pub use cached::CachedPrefix;
+pub use cf::CfPrefix;
+pub use codec::{Bincode, BincodeConfig, Codec, Endian, IntEncoding};
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+pub use index::Index;
+pub use merge::MergeOperators;
+pub use multi_get::{MultiGet, MultiGetKey, MultiGetValues};
+pub use raw::RawPrefix;
+pub use snapshot::{Snapshot, SnapshotPrefix};
+pub use transaction::Transaction;
+pub use ttl::TtlPrefix;
 
 /// Errors that can occur.
 #[derive(Debug)]
 pub enum ErrorKind {
     Bincode(bincode::Error),
     Rocksdb(rocksdb::Error),
+    /// Reading the files of the database failed.
+    Io(io::Error),
+    /// The checksum of the value stored under `key` does not match, see `Prefix::checksummed`.
+    Corruption { key: Vec<u8> },
+    /// The prefix was created with other key and value types, see `DB::checked_prefix`.
+    TypeMismatch { expected: String, found: String },
+    /// The same prefix was requested both as a `Prefix` and as a `PrefixGroup`, see
+    /// `DB::prefix_group`.
+    PrefixConflict { prefix: Vec<u8> },
+    /// The value stored under `key` was changed by someone else during a transaction, see
+    /// `Transaction::commit`.
+    Conflict { key: Vec<u8> },
+    /// The database was opened with another bincode configuration than the one it uses, see
+    /// `DB::open_with_config`.
+    ConfigMismatch { expected: BincodeConfig, found: BincodeConfig },
+    /// A serialized key of `size` bytes is larger than the limit of the database, see
+    /// `DB::max_key_size`.
+    KeyTooLarge { size: usize, limit: usize },
+    /// A serialized value of `size` bytes is larger than the limit of the database, see
+    /// `DB::max_value_size`.
+    ValueTooLarge { size: usize, limit: usize },
+    /// `key` is not ordered after the key before it, see `Prefix::ingest_sorted`.
+    Unsorted { key: Vec<u8> },
+    /// The values of the serialized prefix `prefix` can not be merged, as no merge functions
+    /// were registered for it or the prefix is checksummed or canonical, see `MergeOperators`.
+    MergeNotSupported { prefix: Vec<u8> },
+    /// Too many databases with merge operators are open in this process at once, see
+    /// `DB::open_with_merge_operators`.
+    TooManyMergeDatabases,
+    /// A key was read from the values of another `MultiGet` than the one it was added to, see
+    /// `MultiGetValues::get`.
+    ForeignMultiGetKey,
+    /// A prefix of another database was given where one of the same database is needed, see
+    /// `Prefix::partition_by` and `Prefix::indexed`.
+    ForeignPrefix,
+    /// A custom codec failed, see `Codec`, or reading or writing JSON failed, see
+    /// `Prefix::dump_json`.
+    Codec(Box<dyn error::Error + Send + Sync>),
+    /// The runtime was shut down before a function of `AsyncPrefix` could run. Only returned
+    /// with the `async` feature, but always declared so matches on `ErrorKind` do not depend on
+    /// the enabled features.
+    Cancelled,
 }
 
 pub type Error = Box<ErrorKind>;
 
+impl ErrorKind {
+    /// Returns `true` if serializing or deserializing with bincode failed.
+    pub fn is_bincode(&self) -> bool {
+        self.as_bincode().is_some()
+    }
+
+    /// Returns `true` if rocksdb failed.
+    pub fn is_rocksdb(&self) -> bool {
+        self.as_rocksdb().is_some()
+    }
+
+    /// The bincode error, if serializing or deserializing with bincode failed.
+    pub fn as_bincode(&self) -> Option<&bincode::Error> {
+        match *self {
+            ErrorKind::Bincode(ref e) => Some(e),
+            _ => None,
+        }
+    }
+
+    /// The rocksdb error, if rocksdb failed.
+    pub fn as_rocksdb(&self) -> Option<&rocksdb::Error> {
+        match *self {
+            ErrorKind::Rocksdb(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
 type Result<T> = ::std::result::Result<T, Error>;
 
 impl From<bincode::Error> for Error {
@@ -72,11 +186,51 @@ impl From<rocksdb::Error> for Error {
     }
 }
 
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Box::new(ErrorKind::Io(e))
+    }
+}
+
+#[cfg(feature = "json")]
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Error {
+        Box::new(ErrorKind::Codec(Box::new(e)))
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match **self {
             ErrorKind::Bincode(ref e) => write!(f, "bincode error: {}", e),
             ErrorKind::Rocksdb(ref e) => write!(f, "rocksdb error: {}", e),
+            ErrorKind::Io(ref e) => write!(f, "io error: {}", e),
+            ErrorKind::Corruption { ref key } => write!(f, "checksum mismatch for value of key {:?}", key),
+            ErrorKind::TypeMismatch { ref expected, ref found } => {
+                write!(f, "prefix has types {}, expected {}", found, expected)
+            }
+            ErrorKind::Conflict { ref key } => write!(f, "transaction conflict on key {:?}", key),
+            ErrorKind::ConfigMismatch { ref expected, ref found } => {
+                write!(f, "database uses bincode config {:?}, expected {:?}", found, expected)
+            }
+            ErrorKind::KeyTooLarge { size, limit } => {
+                write!(f, "key of {} bytes is larger than the limit of {} bytes", size, limit)
+            }
+            ErrorKind::ValueTooLarge { size, limit } => {
+                write!(f, "value of {} bytes is larger than the limit of {} bytes", size, limit)
+            }
+            ErrorKind::Unsorted { ref key } => write!(f, "key {:?} is not sorted", key),
+            ErrorKind::MergeNotSupported { ref prefix } => {
+                write!(f, "values of prefix {:?} can not be merged", prefix)
+            }
+            ErrorKind::TooManyMergeDatabases => write!(f, "too many databases with merge operators are open"),
+            ErrorKind::ForeignMultiGetKey => write!(f, "key was added to another multi get"),
+            ErrorKind::ForeignPrefix => write!(f, "prefix belongs to another database"),
+            ErrorKind::PrefixConflict { ref prefix } => {
+                write!(f, "prefix {:?} is used both by a prefix and a prefix group", prefix)
+            }
+            ErrorKind::Codec(ref e) => write!(f, "codec error: {}", e),
+            ErrorKind::Cancelled => write!(f, "blocking task was cancelled"),
         }
     }
 }
@@ -86,6 +240,21 @@ impl error::Error for Error {
         match **self {
             ErrorKind::Bincode(ref e) => Some(e),
             ErrorKind::Rocksdb(ref e) => Some(e),
+            ErrorKind::Io(ref e) => Some(e),
+            ErrorKind::Corruption { .. } => None,
+            ErrorKind::TypeMismatch { .. } => None,
+            ErrorKind::Conflict { .. } => None,
+            ErrorKind::ConfigMismatch { .. } => None,
+            ErrorKind::KeyTooLarge { .. } => None,
+            ErrorKind::ValueTooLarge { .. } => None,
+            ErrorKind::Unsorted { .. } => None,
+            ErrorKind::MergeNotSupported { .. } => None,
+            ErrorKind::TooManyMergeDatabases => None,
+            ErrorKind::ForeignMultiGetKey => None,
+            ErrorKind::ForeignPrefix => None,
+            ErrorKind::PrefixConflict { .. } => None,
+            ErrorKind::Codec(ref e) => Some(&**e),
+            ErrorKind::Cancelled => None,
         }
     }
 }
@@ -98,19 +267,251 @@ impl error::Error for Error {
 #[derive(Clone)]
 pub struct DB {
     db: Arc<rocksdb::DB>,
+    update_lock: Arc<Mutex<()>>,
+    /// The prefixes of `DB::ttl_prefix`, whose expired entries are removed during compaction.
+    ttl_prefixes: Arc<RwLock<Vec<Vec<u8>>>>,
+    namespaces: Namespaces,
+    config: BincodeConfig,
+    limits: SizeLimits,
+}
+
+impl fmt::Debug for DB {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DB").field("path", &self.path()).finish()
+    }
 }
 
 impl DB {
-    /// Open a database at `path`.
+    /// Open a database at `path`, creating it if it does not exist.
     pub fn open<P: AsRef<Path>>(path: P) -> Result<DB> {
+        let mut opts = rocksdb::Options::default();
+        opts.create_if_missing(true);
+        DB::open_with_options(path, opts)
+    }
+
+    /// Open a database at `path` with custom rocksdb options, e.g., to tune compression or the
+    /// size of the write buffer.
+    ///
+    /// The compaction filter of `opts` is replaced by the one removing expired entries of
+    /// `DB::ttl_prefix`, and so is the merge operator if the database is opened with
+    /// `DB::open_with_merge_operators`. The column families of `DB::cf_prefix` are opened with the
+    /// default rocksdb options.
+    ///
+    /// A database containing values of `Prefix::merge` must always be opened with
+    /// `DB::open_with_merge_operators`, see `MergeOperators`.
+    ///
+    /// `opts` must not set a prefix extractor. Iterators are created with the default read
+    /// options, which can not be changed, and seeks across prefixes, e.g., by reverse iteration,
+    /// may then skip entries.
+    ///
+    /// # Examples
+    /// ```
+    /// extern crate rocksdb;
+    /// # extern crate rocksbin;
+    ///
+    /// # fn main() {
+    /// let mut opts = rocksdb::Options::default();
+    /// opts.create_if_missing(true);
+    /// opts.set_write_buffer_size(16 * 1024 * 1024);
+    ///
+    /// let db = rocksbin::DB::open_with_options("db_dir_open_with_options", opts).unwrap();
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_open_with_options").unwrap();
+    /// # }
+    /// ```
+    pub fn open_with_options<P: AsRef<Path>>(path: P, opts: rocksdb::Options) -> Result<DB> {
+        DB::open_with_config(path, opts, BincodeConfig::default())
+    }
+
+    /// Open a database at `path` with custom rocksdb options, serializing keys and values with the
+    /// bincode configuration `config` instead of the default one, e.g., to store small integers
+    /// in fewer bytes.
+    ///
+    /// Every prefix of the database uses `config`, except for the keys of `Prefix::ordered`
+    /// prefixes, the values of `Prefix::canonical` prefixes and values of prefixes with another
+    /// `Codec`. A configuration other than the default is stored in the database, and opening it
+    /// with another configuration returns `ErrorKind::ConfigMismatch`. So does opening a database
+    /// that already contains keys with a configuration other than the default.
+    ///
+    /// See `DB::open_with_options`
+    ///
+    /// # Examples
+    /// ```
+    /// extern crate rocksdb;
+    /// # extern crate rocksbin;
+    ///
+    /// use rocksbin::{BincodeConfig, IntEncoding};
+    ///
+    /// # fn main() {
+    /// let mut opts = rocksdb::Options::default();
+    /// opts.create_if_missing(true);
+    ///
+    /// let config = BincodeConfig {
+    ///     int_encoding: IntEncoding::Varint,
+    ///     ..BincodeConfig::default()
+    /// };
+    ///
+    /// let db = rocksbin::DB::open_with_config("db_dir_open_with_config", opts, config).unwrap();
+    /// let heights = db.prefix::<String, u64>(b"heights").unwrap();
+    ///
+    /// heights.insert("John", &175).unwrap();
+    /// assert_eq!(heights.get("John").unwrap(), Some(175));
+    ///
+    /// # drop(heights);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_open_with_config").unwrap();
+    /// # }
+    /// ```
+    pub fn open_with_config<P: AsRef<Path>>(path: P, opts: rocksdb::Options, config: BincodeConfig) -> Result<DB> {
+        DB::open_with_merge_operators(path, opts, config, MergeOperators::new())
+    }
+
+    /// Open a database at `path` with custom rocksdb options and bincode configuration, and the
+    /// merge functions of its merge prefixes, see `MergeOperators`.
+    ///
+    /// See `DB::open_with_config`
+    ///
+    /// This function will return `Err` if one of the following occures:
+    /// - Opening the database fails, like for `DB::open_with_config`
+    /// - Too many databases with merge operators are open in this process at once
+    pub fn open_with_merge_operators<P: AsRef<Path>>(
+        path: P,
+        mut opts: rocksdb::Options,
+        config: BincodeConfig,
+        merges: MergeOperators,
+    ) -> Result<DB> {
+        let merges = merges.build(config);
+        let claim = if merges.is_empty() {
+            None
+        } else {
+            let claim = merge::Claim::new(merges)?;
+            claim.set_merge_operator(&mut opts);
+            Some(claim)
+        };
+
+        let ttl_prefixes = Arc::new(RwLock::new(Vec::new()));
+        opts.set_compaction_filter(ttl::FILTER_NAME, ttl::compaction_filter(ttl_prefixes.clone(), config));
+
+        // Every column family has to be opened, see `DB::cf_prefix`
+        let cfs = rocksdb::DB::list_cf(&opts, path.as_ref()).unwrap_or_default();
+        let db = if cfs.len() > 1 {
+            let cfs = cfs
+                .into_iter()
+                .map(|name| {
+                    let mut cf_opts = rocksdb::Options::default();
+                    if name == "default" {
+                        cf_opts.set_compaction_filter(
+                            ttl::FILTER_NAME,
+                            ttl::compaction_filter(ttl_prefixes.clone(), config),
+                        );
+                    }
+                    rocksdb::ColumnFamilyDescriptor::new(name, cf_opts)
+                })
+                .collect();
+            rocksdb::DB::open_cf_descriptors(&opts, path, cfs)?
+        } else {
+            rocksdb::DB::open(&opts, path)?
+        };
+
+        // Only a configuration other than the default is stored, so databases that never used
+        // one do not get an extra key
+        let found = match db.get(CONFIG_KEY)? {
+            Some(data) => BincodeConfig::from_bytes(&data).ok_or_else(|| ErrorKind::Corruption {
+                key: CONFIG_KEY.to_vec(),
+            })?,
+            None => {
+                let mut db_iter = db.raw_iterator();
+                db_iter.seek_to_first();
+                if config != BincodeConfig::default() && !db_iter.valid() {
+                    db.put(CONFIG_KEY, &config.to_bytes())?;
+                    config
+                } else {
+                    BincodeConfig::default()
+                }
+            }
+        };
+        if found != config {
+            return Err(Box::new(ErrorKind::ConfigMismatch { expected: config, found }));
+        }
+
+        let db = Arc::new(db);
+        if let Some(claim) = claim {
+            claim.opened(&db);
+        }
+
         Ok(DB {
-            db: Arc::new(rocksdb::DB::open_default(path)?),
+            db,
+            update_lock: Arc::new(Mutex::new(())),
+            ttl_prefixes,
+            namespaces: Namespaces::default(),
+            config,
+            limits: SizeLimits::default(),
         })
     }
 
+    /// Limit the size of serialized keys of prefixes created from this database, or its clones,
+    /// afterwards to `limit` bytes, not counting the prefix itself.
+    ///
+    /// Every function taking a key returns `ErrorKind::KeyTooLarge` for a larger key, before
+    /// serializing it.
+    pub fn max_key_size(mut self, limit: usize) -> DB {
+        self.limits.key = Some(limit);
+        self
+    }
+
+    /// Limit the size of serialized values of prefixes created from this database, or its
+    /// clones, afterwards to `limit` bytes, not counting checksums.
+    ///
+    /// Functions writing a larger value return `ErrorKind::ValueTooLarge` instead, after
+    /// serializing it but before writing anything.
+    ///
+    /// # Examples
+    /// ```
+    /// use rocksbin::ErrorKind;
+    ///
+    /// let db = rocksbin::DB::open("db_dir_max_value_size").unwrap().max_value_size(1024);
+    /// let files = db.prefix::<String, Vec<u8>>(b"files").unwrap();
+    ///
+    /// files.insert("small", &vec![0; 100]).unwrap();
+    ///
+    /// match *files.insert("large", &vec![0; 2000]).unwrap_err() {
+    ///     ErrorKind::ValueTooLarge { limit, .. } => assert_eq!(limit, 1024),
+    ///     _ => panic!("expected ValueTooLarge"),
+    /// }
+    /// assert_eq!(files.get("large").unwrap(), None);
+    ///
+    /// # drop(files);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_max_value_size").unwrap();
+    /// ```
+    pub fn max_value_size(mut self, limit: usize) -> DB {
+        self.limits.value = Some(limit);
+        self
+    }
+
+    /// Delete the database at `path` and all of its files.
+    ///
+    /// The database must not be open.
+    pub fn destroy<P: AsRef<Path>>(path: P, opts: rocksdb::Options) -> Result<()> {
+        rocksdb::DB::destroy(&opts, path)?;
+        Ok(())
+    }
+
+    /// Try to recover as much data as possible from a corrupted database at `path`.
+    ///
+    /// The database must not be open.
+    pub fn repair<P: AsRef<Path>>(path: P, opts: rocksdb::Options) -> Result<()> {
+        rocksdb::DB::repair(opts, path)?;
+        Ok(())
+    }
+
     /// Create a prefix where you can store data.
     ///
-    /// Prefixes can safely be prefixes of each other as seen in the example.
+    /// Prefixes can safely be prefixes of each other as seen in the example. The value type is
+    /// not required to be serializable here, but only `Prefix::contains_key` can be used without.
+    ///
+    /// This function will return `Err` if `prefix` is already used by a `PrefixGroup`, see
+    /// `DB::prefix_group`.
     ///
     /// # Examples
     /// ```
@@ -130,41 +531,284 @@ impl DB {
     /// # drop(db);
     /// # std::fs::remove_dir_all("data").unwrap();
     /// ```
-    pub fn prefix<K: Serialize + DeserializeOwned, V: Serialize + DeserializeOwned>(
-        &self,
-        prefix: &[u8],
-    ) -> Result<Prefix<K, V>> {
-        // No point in using 64bit lenght here
-        // This will never fail
-        let mut prefix_vec = bincode::serialize(&(prefix.len() as u32)).unwrap();
-        prefix_vec.extend_from_slice(&prefix);
+    pub fn prefix<K: Serialize + DeserializeOwned, V>(&self, prefix: &[u8]) -> Result<Prefix<K, V>> {
+        let prefix_vec = serialize_prefix(prefix);
+        self.namespaces.claim(&prefix_vec, prefix, Namespace::Prefix)?;
 
         Ok(Prefix {
             db: self.db.clone(),
+            update_lock: self.update_lock.clone(),
             prefix: prefix_vec,
+            canonical: false,
+            checksum: false,
+            keys: self.config,
+            values: self.config,
+            limits: self.limits,
+            _k: PhantomData,
+            _v: PhantomData,
+            _c: PhantomData,
+        })
+    }
+
+    /// Create a prefix whose values are serialized with the codec `C` instead of bincode.
+    ///
+    /// See `Codec`
+    pub fn prefix_with_codec<K, V, C>(&self, prefix: &[u8]) -> Result<Prefix<K, V, C>>
+    where
+        K: Serialize + DeserializeOwned,
+        V: Serialize + DeserializeOwned,
+        C: Codec,
+    {
+        let prefix = self.prefix::<K, V>(prefix)?;
+
+        Ok(Prefix {
+            db: prefix.db,
+            update_lock: prefix.update_lock,
+            prefix: prefix.prefix,
+            canonical: false,
+            checksum: false,
+            keys: prefix.keys,
+            values: prefix.values,
+            limits: prefix.limits,
             _k: PhantomData,
             _v: PhantomData,
+            _c: PhantomData,
         })
     }
 
+    /// Create a prefix whose values can be updated with `Prefix::merge`, using the merge
+    /// functions registered for it when the database was opened, see `MergeOperators`.
+    ///
+    /// This function will return `Err` if one of the following occures:
+    /// - No merge functions were registered for the prefix
+    /// - The merge functions were registered for another value type than `V`
+    pub fn merge_prefix<K, V>(&self, prefix: &[u8]) -> Result<Prefix<K, V>>
+    where
+        K: Serialize + DeserializeOwned,
+        V: Serialize + DeserializeOwned,
+    {
+        let prefix = self.prefix::<K, V>(prefix)?;
+
+        match merge::value_type(&self.db, &prefix.prefix) {
+            Some(found) if found == type_name::<V>() => Ok(prefix),
+            Some(found) => Err(Box::new(ErrorKind::TypeMismatch {
+                expected: type_name::<V>().to_string(),
+                found: found.to_string(),
+            })),
+            None => Err(Box::new(ErrorKind::MergeNotSupported { prefix: prefix.prefix })),
+        }
+    }
+
+    /// Create a prefix of counters that are incremented with `Prefix::merge`, registered with
+    /// `MergeOperators::counter` when the database was opened.
+    ///
+    /// Merging adds the operand to the counter, which starts at 0. Unlike `Prefix::modify` this
+    /// does not read the counter, so concurrent increments are never lost.
+    ///
+    /// This function will return `Err` in the same cases as `DB::merge_prefix`
+    ///
+    /// # Examples
+    /// ```
+    /// use rocksbin::{BincodeConfig, MergeOperators, DB};
+    ///
+    /// # fn main() {
+    /// let mut opts = rocksdb::Options::default();
+    /// opts.create_if_missing(true);
+    /// let merges = MergeOperators::new().counter(b"visits");
+    ///
+    /// let db = DB::open_with_merge_operators("db_dir_counter_prefix", opts, BincodeConfig::default(), merges).unwrap();
+    /// let visits = db.counter_prefix::<String>(b"visits").unwrap();
+    ///
+    /// visits.merge("/index.html", &1).unwrap();
+    /// visits.merge("/index.html", &1).unwrap();
+    ///
+    /// assert_eq!(visits.get("/index.html").unwrap(), Some(2));
+    ///
+    /// # drop(visits);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_counter_prefix").unwrap();
+    /// # }
+    /// ```
+    pub fn counter_prefix<K: Serialize + DeserializeOwned>(&self, prefix: &[u8]) -> Result<Prefix<K, i64>> {
+        self.merge_prefix(prefix)
+    }
+
+    /// Create a prefix like `DB::prefix`, but check that it is always opened with the same key
+    /// and value types.
+    ///
+    /// The type names of `K` and `V` are stored in the database the first time the prefix is
+    /// created. Opening it again with other types returns `ErrorKind::TypeMismatch` instead of
+    /// failing to deserialize the entries later on. Type names are only compared as strings, so
+    /// renaming or moving a type also counts as a mismatch.
+    ///
+    /// # Examples
+    /// ```
+    /// # let db = rocksbin::DB::open("db_dir_checked_prefix").unwrap();
+    /// let heights = db.checked_prefix::<String, u64>(b"heights").unwrap();
+    ///
+    /// assert!(db.checked_prefix::<String, u64>(b"heights").is_ok());
+    /// assert!(db.checked_prefix::<String, String>(b"heights").is_err());
+    ///
+    /// # drop(heights);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_checked_prefix").unwrap();
+    /// ```
+    pub fn checked_prefix<K, V>(&self, prefix: &[u8]) -> Result<Prefix<K, V>>
+    where
+        K: Serialize + DeserializeOwned,
+        V: Serialize + DeserializeOwned,
+    {
+        let _guard = self.update_lock.lock().unwrap();
+        self.check_types::<K, V>(TYPES_PREFIX, prefix, "Prefix")?;
+
+        self.prefix(prefix)
+    }
+
+    /// Store the type names of `K` and `V` under `name` in the prefix `types`, or check that they
+    /// are the same as the stored ones if there are any. `update_lock` must be held.
+    fn check_types<K, V>(&self, types: &[u8], name: &[u8], kind: &str) -> Result<()> {
+        let mut types = self.prefix::<Vec<u8>, String>(types)?;
+        // The limits are for user data, not for long type names
+        types.limits = SizeLimits::default();
+        let expected = format!("{}<{}, {}>", kind, type_name::<K>(), type_name::<V>());
+
+        match types.get(name)? {
+            Some(ref found) if *found != expected => Err(Box::new(ErrorKind::TypeMismatch {
+                expected,
+                found: found.clone(),
+            })),
+            Some(_) => Ok(()),
+            None => types.insert(name, &expected),
+        }
+    }
+
+    /// Create a prefix whose values are stored as raw bytes.
+    ///
+    /// See `RawPrefix`
+    pub fn raw_prefix<K: Serialize + DeserializeOwned>(&self, prefix: &[u8]) -> Result<RawPrefix<K>> {
+        Ok(RawPrefix::new(self.prefix(prefix)?))
+    }
+
+    /// Create a prefix where entries can expire.
+    ///
+    /// See `TtlPrefix`
+    pub fn ttl_prefix<K: Serialize + DeserializeOwned, V: Serialize + DeserializeOwned>(
+        &self,
+        prefix: &[u8],
+    ) -> Result<TtlPrefix<K, V>> {
+        let prefix = self.prefix(prefix)?;
+
+        let mut ttl_prefixes = self.ttl_prefixes.write().unwrap();
+        if !ttl_prefixes.contains(&prefix.prefix) {
+            ttl_prefixes.push(prefix.prefix.clone());
+        }
+
+        Ok(TtlPrefix::new(prefix))
+    }
+
+    /// Create a prefix backed by its own column family called `name`, creating the column family
+    /// if it does not exist.
+    ///
+    /// Like with `DB::checked_prefix` the type names of `K` and `V` are stored the first time the
+    /// column family is opened, and opening it again with other types returns
+    /// `ErrorKind::TypeMismatch`.
+    ///
+    /// See `CfPrefix`
+    ///
+    /// # Examples
+    /// ```
+    /// # let db = rocksbin::DB::open("db_dir_cf_prefix").unwrap();
+    /// let heights = db.cf_prefix::<String, u64>("heights").unwrap();
+    ///
+    /// heights.insert("John", &175).unwrap();
+    ///
+    /// assert_eq!(heights.get("John").unwrap(), Some(175));
+    ///
+    /// # drop(heights);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_cf_prefix").unwrap();
+    /// ```
+    pub fn cf_prefix<K, V>(&self, name: &str) -> Result<CfPrefix<K, V>>
+    where
+        K: Serialize + DeserializeOwned,
+        V: Serialize + DeserializeOwned,
+    {
+        self.cf_prefix_with_options(name, rocksdb::Options::default())
+    }
+
+    /// Create a prefix backed by its own column family called `name`, creating the column family
+    /// with the rocksdb options `opts` if it does not exist, e.g., to give it a bloom filter or a
+    /// block cache of its own.
+    ///
+    /// `opts` are only used if the column family is created, and a database is always opened with
+    /// the default options for its column families, see `DB::open_with_options`.
+    ///
+    /// # Examples
+    /// ```
+    /// extern crate rocksdb;
+    /// # extern crate rocksbin;
+    ///
+    /// # fn main() {
+    /// # let db = rocksbin::DB::open("db_dir_cf_prefix_with_options").unwrap();
+    /// let mut table_opts = rocksdb::BlockBasedOptions::default();
+    /// table_opts.set_bloom_filter(10, false);
+    /// table_opts.set_lru_cache(8 * 1024 * 1024);
+    ///
+    /// let mut opts = rocksdb::Options::default();
+    /// opts.set_block_based_table_factory(&table_opts);
+    ///
+    /// let heights = db.cf_prefix_with_options::<String, u64>("heights", opts).unwrap();
+    ///
+    /// heights.insert("John", &175).unwrap();
+    ///
+    /// assert_eq!(heights.get("John").unwrap(), Some(175));
+    ///
+    /// # drop(heights);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_cf_prefix_with_options").unwrap();
+    /// # }
+    /// ```
+    pub fn cf_prefix_with_options<K, V>(&self, name: &str, opts: rocksdb::Options) -> Result<CfPrefix<K, V>>
+    where
+        K: Serialize + DeserializeOwned,
+        V: Serialize + DeserializeOwned,
+    {
+        let _guard = self.update_lock.lock().unwrap();
+        self.check_types::<K, V>(CF_TYPES_PREFIX, name.as_bytes(), "CfPrefix")?;
+        if self.db.cf_handle(name).is_none() {
+            self.db.create_cf(name, &opts)?;
+        }
+
+        Ok(CfPrefix::new(self.db.clone(), name, self.config, self.limits))
+    }
+
     /// Create a prefix group.
     ///
-    /// It is important that a `PrefixGroup` never has the same prefix as `Prefix`, if they do you
-    /// might get key parse errors
+    /// A `PrefixGroup` must never have the same prefix as a `Prefix`, otherwise the keys of the
+    /// group would be read as keys of the prefix. Requesting a group with the same prefix as a
+    /// `Prefix` created from this database before, or the other way around, therefore returns
+    /// `ErrorKind::PrefixConflict`. Only prefixes created since the database was opened are
+    /// checked.
     pub fn prefix_group(&self, prefix: &[u8]) -> Result<PrefixGroup> {
-        // No point in using 64bit lenght here
-        // This will never fail
-        let mut prefix_vec = bincode::serialize(&(prefix.len() as u32)).unwrap();
-        prefix_vec.extend_from_slice(&prefix);
+        let prefix_vec = serialize_prefix(prefix);
+        self.namespaces.claim(&prefix_vec, prefix, Namespace::Group)?;
 
         Ok(PrefixGroup {
             db: self.db.clone(),
+            update_lock: self.update_lock.clone(),
             prefix: prefix_vec,
+            namespaces: self.namespaces.clone(),
+            config: self.config,
+            limits: self.limits,
         })
     }
 
     /// Import database data from a deserializer.
     ///
+    /// The bincode configuration and the types stored by `DB::checked_prefix` and `DB::cf_prefix`
+    /// are neither serialized nor imported, so this database keeps its own. The data has to be
+    /// imported into a database opened with the same `BincodeConfig` it was serialized from.
+    ///
     /// # Examples
     /// ```
     /// extern crate serde_json;
@@ -197,333 +841,3655 @@ impl DB {
 
         Ok(())
     }
-}
-
-struct DBVisitor {
-    db: DB,
-}
 
-impl<'de> Visitor<'de> for DBVisitor {
-    type Value = ();
+    /// Change mutable options of the database at runtime, e.g.,
+    /// `db.set_options(&[("write_buffer_size", "8388608")])`.
+    ///
+    /// All prefixes share the same rocksdb column family, so the options apply to every prefix.
+    /// Only options rocksdb can change without reopening the database are accepted, setting any
+    /// other option returns `Err`.
+    ///
+    /// The options of `DB::cf_prefix` column families are not changed. The rocksdb bindings can
+    /// only change the options of the default column family, use `DB::cf_prefix_with_options` to
+    /// tune a column family when it is created.
+    pub fn set_options(&self, opts: &[(&str, &str)]) -> Result<()> {
+        self.db.set_options(opts)?;
+        Ok(())
+    }
 
-    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        write!(formatter, "a database seq")
+    /// Take a snapshot of the database.
+    ///
+    /// The snapshot is a consistent view of the database at the point of creation, writes made
+    /// after it was taken are not visible through it.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot::new(self.clone())
     }
 
-    fn visit_seq<A>(self, mut map: A) -> std::result::Result<(), A::Error> 
-        where A: SeqAccess<'de>
-    {
-        while let Some((key, value)) = map.next_element::<(Vec<_>, Vec<_>)>()? {
-            self.db.db.put(&key, &value).map_err(|e| serde::de::Error::custom(e))?;
-        }
+    /// Start a transaction, see `Transaction`.
+    pub fn transaction(&self) -> Transaction {
+        Transaction::new(self.clone())
+    }
 
-        Ok(())
+    /// Create an empty set of keys to read together, see `MultiGet`.
+    pub fn multi_get(&self) -> MultiGet {
+        MultiGet::new()
     }
 
-}
+    /// Read the keys of `reads` together, from the same snapshot.
+    ///
+    /// The rocksdb bindings have no multi get, so the keys are read one by one from a snapshot.
+    ///
+    /// This function will return `Err` if the underlying rocksdb command fails.
+    pub fn get_many(&self, reads: MultiGet) -> Result<MultiGetValues> {
+        let snapshot = self.db.snapshot();
+        let values = reads
+            .keys()
+            .iter()
+            .map(|key| Ok(snapshot.get(key)?.map(|data| data.to_vec())))
+            .collect::<Result<Vec<_>>>()?;
 
-impl Serialize for DB {
-    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
-        let mut iter = self.db.raw_iterator();
-        iter.seek_to_first();
+        Ok(reads.into_values(values))
+    }
 
-        let mut map = serializer.serialize_seq(None)?;
+    /// Create an empty batch of writes, see `Batch`.
+    pub fn batch(&self) -> Batch {
+        Batch {
+            batch: rocksdb::WriteBatch::default(),
+        }
+    }
 
-        while iter.valid() {
-            if let (Some(key), Some(value)) = (iter.key(), iter.value()) {
-                map.serialize_element(&(key,value))?;
-            }
-            iter.next();
+    /// Build a batch of writes by chaining calls, see `BatchBuilder`.
+    pub fn write_batch(&self) -> BatchBuilder<'_> {
+        BatchBuilder {
+            db: self,
+            batch: self.batch(),
+            error: None,
         }
+    }
 
-        map.end()
+    /// Write a batch atomically, either all of its writes are applied or none of them are.
+    pub fn write(&self, batch: Batch) -> Result<()> {
+        self.db.write(batch.batch)?;
+        Ok(())
     }
-}
 
-/// A way to group prefixes.
-#[derive(Clone)]
-pub struct PrefixGroup {
-    db: Arc<rocksdb::DB>,
-    prefix: Vec<u8>,
-}
-
-impl PrefixGroup {
-    /// Create a prefix inside this prefix group.
+    /// Write a batch atomically like `DB::write`, and wait until it is synced to disk.
     ///
-    /// See `DB::prefix`
-    pub fn prefix<K: Serialize + DeserializeOwned, V: Serialize + DeserializeOwned>(
-        &self,
-        prefix: &[u8],
-    ) -> Result<Prefix<K, V>> {
-        // No point in using 64bit lenght here
-        // This will never fail
-        let mut prefix_vec = self.prefix.clone();
-        bincode::serialize_into(&mut prefix_vec, &(prefix.len() as u32))?;
-        prefix_vec.extend_from_slice(&prefix);
+    /// Normal writes survive a crash of the process, but can be lost if the machine crashes
+    /// before the operating system has written them to disk.
+    pub fn write_sync(&self, batch: Batch) -> Result<()> {
+        let mut opts = rocksdb::WriteOptions::default();
+        opts.set_sync(true);
 
-        Ok(Prefix {
-            db: self.db.clone(),
-            prefix: prefix_vec,
-            _k: PhantomData,
-            _v: PhantomData,
-        })
+        self.db.write_opt(batch.batch, &opts)?;
+        Ok(())
     }
 
-    /// Create a sub prefix group.
+    /// Create a point-in-time copy of the whole database in the directory `target`, which must
+    /// not exist yet.
     ///
-    /// See `DB::prefix_group`
-    pub fn prefix_group(&self, prefix: &[u8]) -> Result<PrefixGroup> {
-        // No point in using 64bit lenght here
-        // This will never fail
-        let mut prefix_vec = self.prefix.clone();
-        bincode::serialize_into(&mut prefix_vec, &(prefix.len() as u32))?;
-        prefix_vec.extend_from_slice(&prefix);
+    /// Files are hard-linked where possible, so this is cheap and does not block writes. The copy
+    /// can be opened with `DB::open` like any other database.
+    ///
+    /// # Examples
+    /// ```
+    /// # let db = rocksbin::DB::open("db_dir_checkpoint").unwrap();
+    /// let heights = db.prefix::<String, u64>(b"heights").unwrap();
+    ///
+    /// heights.insert("John", &175).unwrap();
+    /// db.checkpoint("db_dir_checkpoint_copy").unwrap();
+    ///
+    /// let copy = rocksbin::DB::open("db_dir_checkpoint_copy").unwrap();
+    /// let copied_heights = copy.prefix::<String, u64>(b"heights").unwrap();
+    ///
+    /// assert_eq!(copied_heights.get("John").unwrap(), Some(175));
+    ///
+    /// # drop(copied_heights);
+    /// # drop(copy);
+    /// # drop(heights);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_checkpoint").unwrap();
+    /// # std::fs::remove_dir_all("db_dir_checkpoint_copy").unwrap();
+    /// ```
+    pub fn checkpoint<P: AsRef<Path>>(&self, target: P) -> Result<()> {
+        let checkpoint = rocksdb::checkpoint::Checkpoint::new(&self.db)?;
+        checkpoint.create_checkpoint(target)?;
+        Ok(())
+    }
 
-        Ok(PrefixGroup {
-            db: self.db.clone(),
-            prefix: prefix_vec,
-        })
+    /// Compact the whole database, reclaiming the space of removed and overwritten entries.
+    ///
+    /// This can take a long time on large databases. The rocksdb bindings have no way to only
+    /// flush the memtables, but compacting also writes them to disk.
+    pub fn compact_all(&self) {
+        self.db.compact_range(None, None);
     }
-}
 
-/// A grouping of data in a database.
-///
-/// Most methods of `Prefix` use `Borrow` in a similar fashion like `HashMap`.
-/// This means that if you have a prefix of type `Prefix<String, u64>` you can use both `&String`
-/// and `&str` to access the data.
-#[derive(Clone)]
-pub struct Prefix<K, V> {
-    db: Arc<rocksdb::DB>,
-    prefix: Vec<u8>,
-    _k: PhantomData<K>,
-    _v: PhantomData<V>,
-}
+    /// Returns the size in bytes of the SST files of the database.
+    ///
+    /// This is an estimate of how much disk space the database uses, it does not include the
+    /// write-ahead log, nor writes that are only in memory so far.
+    ///
+    /// This function will return `Err` if the directory of the database can not be read.
+    pub fn live_files_size(&self) -> Result<u64> {
+        Ok(self.sst_files()?.1)
+    }
 
-impl<K: Serialize + DeserializeOwned, V: Serialize + DeserializeOwned> Prefix<K, V> {
-    /// Returns the value coresponing to the key. If there is no such value, `Ok(None)` is returned.
+    /// The number of SST files of the database and their size in bytes.
+    fn sst_files(&self) -> Result<(u64, u64)> {
+        let mut count = 0;
+        let mut size = 0;
+        for entry in fs::read_dir(self.path())? {
+            let entry = entry?;
+            if entry.path().extension() == Some("sst".as_ref()) {
+                count += 1;
+                size += entry.metadata()?.len();
+            }
+        }
+        Ok((count, size))
+    }
+
+    /// Statistics about the database, for monitoring.
     ///
-    /// This function will return `Err` if one of the following occures:
-    /// - Serializing the key fails
-    /// - The underlying rocksdb command fails
-    /// - Deserializing of the value fails
+    /// The rocksdb bindings can not read the properties of a database, so the statistics are
+    /// gathered by rocksbin, and SST files are found in the directory of the database. A
+    /// statistic that can not be gathered is `None`. Keys of `DB::cf_prefix` are not counted.
+    ///
+    /// `num_keys` is an exact count made by visiting every key of the database, so this takes
+    /// O(n) time in the number of keys, unlike the estimate rocksdb keeps. Don't call it on large
+    /// databases in a tight monitoring loop.
+    ///
+    /// The size of the memtables is not reported. It is only known to rocksdb itself, through the
+    /// `rocksdb.cur-size-all-mem-tables` property, which the rocksdb 0.11 bindings can not read.
     ///
     /// # Examples
     /// ```
-    /// # let db = rocksbin::DB::open("db_dir2").unwrap();
+    /// # let db = rocksbin::DB::open("db_dir_stats").unwrap();
     /// let heights = db.prefix::<String, u64>(b"heights").unwrap();
     ///
     /// heights.insert("John", &175).unwrap();
-    /// heights.insert(&"Lisa".to_string(), &165).unwrap();
     ///
-    /// assert_eq!(heights.get("John").unwrap(), Some(175));
-    /// assert_eq!(heights.get("Lisa").unwrap(), Some(165));
+    /// let stats = db.stats().unwrap();
+    /// assert_eq!(stats.num_keys, Some(1));
     ///
     /// # drop(heights);
     /// # drop(db);
-    /// # std::fs::remove_dir_all("db_dir2").unwrap();
+    /// # std::fs::remove_dir_all("db_dir_stats").unwrap();
     /// ```
-    pub fn get<Q>(&self, key: &Q) -> Result<Option<V>>
-    where
-        K: Borrow<Q>,
-        Q: Serialize + ?Sized,
-    {
-        let mut key_buf = self.prefix.clone();
-        key_buf.reserve(bincode::serialized_size(&key)? as usize);
-        bincode::serialize_into(&mut key_buf, &key)?;
-        match self.db.get(&key_buf)? {
-            Some(data) => Ok(Some(bincode::deserialize(&data)?)),
-            None => Ok(None),
+    pub fn stats(&self) -> Result<DbStats> {
+        let mut db_iter = self.db.raw_iterator();
+        db_iter.seek_to_first();
+
+        let mut num_keys = 0;
+        while db_iter.valid() {
+            num_keys += 1;
+            db_iter.next();
         }
+
+        let sst_files = self.sst_files().ok();
+
+        Ok(DbStats {
+            num_keys: Some(num_keys),
+            sst_files: sst_files.map(|(count, _)| count),
+            sst_size: sst_files.map(|(_, size)| size),
+        })
     }
 
-    /// Insert a key-value pair.
+    /// The path the database was opened at.
+    ///
+    /// # Examples
+    /// ```
+    /// let db = rocksbin::DB::open("db_dir_path").unwrap();
+    ///
+    /// assert_eq!(db.path(), std::path::Path::new("db_dir_path"));
+    ///
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_path").unwrap();
+    /// ```
+    pub fn path(&self) -> &Path {
+        self.db.path()
+    }
+
+    /// The underlying rocksdb database, for features rocksbin does not wrap.
+    ///
+    /// Be careful when writing through this handle: rocksbin expects every key of a prefix to be
+    /// the prefix followed by a serialized key, and every value to be a serialized value (with a
+    /// checksum for checksummed prefixes). Breaking this can make reads and iteration over the
+    /// prefix return errors. Writes through the handle also bypass the lock used by functions
+    /// like [`Prefix::update_retry`], so they are not atomic with respect to those.
+    pub fn raw(&self) -> &Arc<rocksdb::DB> {
+        &self.db
+    }
+}
+
+/// A list of writes to any prefixes of a database that are applied atomically and in order.
+///
+/// Nothing is written until the batch is passed to `DB::write`, dropping the batch discards the
+/// writes.
+///
+/// # Examples
+/// ```
+/// # let db = rocksbin::DB::open("db_dir_batch").unwrap();
+/// let heights = db.prefix::<String, u64>(b"heights").unwrap();
+/// let weights = db.prefix::<String, u64>(b"weights").unwrap();
+///
+/// let mut batch = db.batch();
+/// batch.insert(&heights, "John", &175).unwrap();
+/// batch.insert(&weights, "John", &70).unwrap();
+///
+/// assert_eq!(heights.get("John").unwrap(), None);
+///
+/// db.write(batch).unwrap();
+///
+/// assert_eq!(heights.get("John").unwrap(), Some(175));
+/// assert_eq!(weights.get("John").unwrap(), Some(70));
+///
+/// # drop(heights);
+/// # drop(weights);
+/// # drop(db);
+/// # std::fs::remove_dir_all("db_dir_batch").unwrap();
+/// ```
+pub struct Batch {
+    batch: rocksdb::WriteBatch,
+}
+
+impl Batch {
+    /// Add the insertion of a key-value pair into `prefix` to the batch.
     ///
     /// This function will return `Err` if one of the following occures:
-    /// - Serializing the key or the value fails
+    /// - Serializing the key or value fails
     /// - The underlying rocksdb command fails
-    pub fn insert<Q>(&self, key: &Q, value: &V) -> Result<()>
+    pub fn insert<K, V, C, Q>(&mut self, prefix: &Prefix<K, V, C>, key: &Q, value: &V) -> Result<()>
     where
-        K: Borrow<Q>,
+        K: Serialize + DeserializeOwned + Borrow<Q>,
+        V: Serialize + DeserializeOwned,
+        C: Codec,
         Q: Serialize + ?Sized,
     {
-        let mut key_buf = self.prefix.clone();
-        key_buf.reserve(bincode::serialized_size(&key)? as usize);
-        bincode::serialize_into(&mut key_buf, &key)?;
-        let value_buf = bincode::serialize(value)?;
-
-        self.db.put(&key_buf, &value_buf)?;
+        self.batch.put(&prefix.key_buf(key)?, &prefix.value_buf(value)?)?;
         Ok(())
     }
 
-    /// Removes a key-value pair.
+    /// Add the removal of a key from `prefix` to the batch.
     ///
     /// This function will return `Err` if one of the following occures:
     /// - Serializing the key fails
     /// - The underlying rocksdb command fails
-    pub fn remove<Q>(&self, key: &Q) -> Result<()>
+    pub fn remove<K, V, C, Q>(&mut self, prefix: &Prefix<K, V, C>, key: &Q) -> Result<()>
     where
-        K: Borrow<Q>,
+        K: Serialize + DeserializeOwned + Borrow<Q>,
+        V: Serialize + DeserializeOwned,
+        C: Codec,
         Q: Serialize + ?Sized,
     {
-        let mut key_buf = self.prefix.clone();
-        key_buf.reserve(bincode::serialized_size(&key)? as usize);
-        bincode::serialize_into(&mut key_buf, &key)?;
-
-        self.db.delete(&key_buf)?;
+        self.batch.delete(&prefix.key_buf(key)?)?;
         Ok(())
     }
 
-    /// Check if this prefix contains a key.
-    ///
-    /// This function will return `Err` in the same cases as `Prefix::get`
-    pub fn contains_key<Q>(&self, key: &Q) -> Result<bool>
+    /// Returns the number of writes in the batch.
+    pub fn len(&self) -> usize {
+        self.batch.len()
+    }
+
+    /// Returns `true` if the batch contains no writes.
+    pub fn is_empty(&self) -> bool {
+        self.batch.is_empty()
+    }
+}
+
+/// Statistics about a database, see `DB::stats`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DbStats {
+    /// The number of keys, including those used internally by rocksbin, counted by visiting
+    /// every key.
+    pub num_keys: Option<u64>,
+    /// The number of SST files.
+    pub sst_files: Option<u64>,
+    /// The size of the SST files in bytes.
+    pub sst_size: Option<u64>,
+}
+
+/// A `Batch` that is built by chaining calls and written with `commit`.
+///
+/// Errors are kept until `commit`, which then returns the first one and writes nothing.
+///
+/// See `DB::write_batch`
+///
+/// # Examples
+/// ```
+/// # let db = rocksbin::DB::open("db_dir_write_batch").unwrap();
+/// let users = db.prefix::<u64, String>(b"users").unwrap();
+/// let sessions = db.prefix::<String, u64>(b"sessions").unwrap();
+///
+/// sessions.insert("abc", &1).unwrap();
+///
+/// db.write_batch()
+///     .put(&users, &1, &"John".to_string())
+///     .delete(&sessions, "abc")
+///     .commit()
+///     .unwrap();
+///
+/// assert_eq!(users.get(&1).unwrap(), Some("John".to_string()));
+/// assert_eq!(sessions.get("abc").unwrap(), None);
+///
+/// # drop(users);
+/// # drop(sessions);
+/// # drop(db);
+/// # std::fs::remove_dir_all("db_dir_write_batch").unwrap();
+/// ```
+pub struct BatchBuilder<'a> {
+    db: &'a DB,
+    batch: Batch,
+    error: Option<Error>,
+}
+
+impl<'a> BatchBuilder<'a> {
+    /// Insert a key-value pair into `prefix`, see `Batch::insert`.
+    pub fn put<K, V, C, Q>(mut self, prefix: &Prefix<K, V, C>, key: &Q, value: &V) -> BatchBuilder<'a>
     where
-        K: Borrow<Q>,
+        K: Serialize + DeserializeOwned + Borrow<Q>,
+        V: Serialize + DeserializeOwned,
+        C: Codec,
         Q: Serialize + ?Sized,
     {
-        self.get(key).map(|v| v.is_some()) // TODO: optimize
+        if self.error.is_none() {
+            self.error = self.batch.insert(prefix, key, value).err();
+        }
+        self
     }
 
-    /// Modify a value coresponing to a key.
-    ///
-    /// This function will return `Err` in the same cases as `Prefix::get` and `Prefix::insert`
-    pub fn modify<Q, F: FnOnce(&mut V)>(&self, key: &Q, f: F) -> Result<()>
+    /// Remove a key from `prefix`, see `Batch::remove`.
+    pub fn delete<K, V, C, Q>(mut self, prefix: &Prefix<K, V, C>, key: &Q) -> BatchBuilder<'a>
     where
-        K: Borrow<Q>,
+        K: Serialize + DeserializeOwned + Borrow<Q>,
+        V: Serialize + DeserializeOwned,
+        C: Codec,
         Q: Serialize + ?Sized,
     {
-        match self.get(key)? {
-            Some(mut value) => {
-                f(&mut value);
-                self.insert(&key, &value)
-            }
-            None => Ok(()),
+        if self.error.is_none() {
+            self.error = self.batch.remove(prefix, key).err();
         }
+        self
     }
 
-    /// An iterator visiting all key-value pairs of this prefix.
-    /// The iterator type is `Result<(K, V), Error>`
-    pub fn iter(&self) -> Iter<K, V> {
-        let mut db_iter = self.db.raw_iterator();
-        db_iter.seek(&self.prefix);
-
-        Iter {
-            db_iter,
-            prefix: self.prefix.clone(),
-            _k: PhantomData,
-            _v: PhantomData,
+    /// Write the batch atomically, see `DB::write`.
+    ///
+    /// This function will return `Err` if one of the following occures:
+    /// - Serializing one of the keys or values failed, in which case nothing is written
+    /// - The underlying rocksdb command fails
+    pub fn commit(self) -> Result<()> {
+        match self.error {
+            Some(e) => Err(e),
+            None => self.db.write(self.batch),
         }
     }
+}
 
-    /// An iterator visiting all keys of this prefix.
-    /// The iterator type is `Result<K, Error>`
-    pub fn keys(&self) -> Keys<K> {
-        let mut db_iter = self.db.raw_iterator();
-        db_iter.seek(&self.prefix);
+/// The largest serialized keys and values the prefixes of a database accept, see
+/// `DB::max_key_size` and `DB::max_value_size`.
+#[derive(Debug, Clone, Copy, Default)]
+struct SizeLimits {
+    key: Option<usize>,
+    value: Option<usize>,
+}
 
-        Keys {
-            db_iter,
-            prefix: self.prefix.clone(),
-            _k: PhantomData,
+impl SizeLimits {
+    /// This function will return `Err` if `size` is larger than the key limit.
+    fn check_key(&self, size: usize) -> Result<()> {
+        match self.key {
+            Some(limit) if size > limit => Err(Box::new(ErrorKind::KeyTooLarge { size, limit })),
+            _ => Ok(()),
         }
     }
 
-    /// An iterator visiting all values of this prefix.
-    /// The iterator type is `Result<V, Error>`
-    pub fn values(&self) -> Values<V> {
-        let mut db_iter = self.db.raw_iterator();
-        db_iter.seek(&self.prefix);
-
-        Values {
-            db_iter,
-            prefix: self.prefix.clone(),
-            _v: PhantomData,
+    /// This function will return `Err` if `size` is larger than the value limit.
+    fn check_value(&self, size: usize) -> Result<()> {
+        match self.value {
+            Some(limit) if size > limit => Err(Box::new(ErrorKind::ValueTooLarge { size, limit })),
+            _ => Ok(()),
         }
     }
 }
 
-/// An iterator over the key-value pairs of a prefix.
-pub struct Iter<K, V> {
-    db_iter: rocksdb::DBRawIterator,
-    prefix: Vec<u8>,
-    _k: PhantomData<K>,
-    _v: PhantomData<V>,
+/// Whether a serialized prefix is used by a `Prefix` or a `PrefixGroup`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Namespace {
+    Prefix,
+    Group,
 }
 
-impl<K: DeserializeOwned, V: DeserializeOwned> Iterator for Iter<K, V> {
-    type Item = Result<(K, V)>; // :(
+/// The serialized prefixes that were requested from a database, to catch a prefix that is used
+/// both by a `Prefix` and a `PrefixGroup`.
+#[derive(Clone, Default)]
+struct Namespaces {
+    used: Arc<Mutex<HashMap<Vec<u8>, Namespace>>>,
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.db_iter.valid() {
-            let k =
-                // We do not reuse the buffer so this is safe
-                unsafe {self.db_iter.key_inner()}
-                    .and_then(|k| if &k[0..self.prefix.len()] == &self.prefix[..] { Some(k) } else { None } )
-                    .map(|k| bincode::deserialize(&k[self.prefix.len()..]));
-            let v =
-                // We do not reuse the buffer so this is safe
-                unsafe {self.db_iter.value_inner()}
-                    .map(|k| bincode::deserialize(k));
-
-            self.db_iter.next();
-            k.and_then(|k| v.map(|v| Ok((k?, v?))))
-        } else {
-            None
+impl Namespaces {
+    /// Record that `prefix_vec`, the serialized form of `prefix`, is used as `namespace`.
+    ///
+    /// This function will return `Err` if it is already used as the other namespace.
+    fn claim(&self, prefix_vec: &[u8], prefix: &[u8], namespace: Namespace) -> Result<()> {
+        let mut used = self.used.lock().unwrap();
+        match used.get(prefix_vec) {
+            Some(&used) if used != namespace => Err(Box::new(ErrorKind::PrefixConflict {
+                prefix: prefix.to_vec(),
+            })),
+            Some(_) => Ok(()),
+            None => {
+                used.insert(prefix_vec.to_vec(), namespace);
+                Ok(())
+            }
         }
     }
 }
 
-/// An iterator over the keys of a prefix.
-pub struct Keys<K> {
-    db_iter: rocksdb::DBRawIterator,
-    prefix: Vec<u8>,
-    _k: PhantomData<K>,
+/// The serialized form of a top level prefix, which all its keys start with.
+fn serialize_prefix(prefix: &[u8]) -> Vec<u8> {
+    // No point in using 64bit lenght here
+    // This will never fail
+    let mut prefix_vec = bincode::serialize(&(prefix.len() as u32)).unwrap();
+    prefix_vec.extend_from_slice(prefix);
+    prefix_vec
 }
 
-impl<K: DeserializeOwned> Iterator for Keys<K> {
-    type Item = Result<K>; // :(
+/// The prefix where `DB::checked_prefix` stores the types of each prefix.
+const TYPES_PREFIX: &[u8] = b"__rocksbin_types";
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.db_iter.valid() {
-            let k =
-                // We do not reuse the buffer so this is safe
-                unsafe {self.db_iter.key_inner()}
-                    .and_then(|k| if &k[0..self.prefix.len()] == &self.prefix[..] { Some(k) } else { None } )
-                    .map(|k| Ok(bincode::deserialize(&k[self.prefix.len()..])?));
-
-            self.db_iter.next();
-            k
-        } else {
-            None
+/// The prefix where `DB::cf_prefix` stores the types of each column family.
+const CF_TYPES_PREFIX: &[u8] = b"__rocksbin_cf_types";
+
+/// The key where `DB::open_with_config` stores the bincode configuration of the database.
+const CONFIG_KEY: &[u8] = b"__rocksbin_config";
+
+/// Returns `true` if `key` is one of the keys rocksbin stores about the database itself, which
+/// `DB::serialize` and `DB::import` leave out.
+fn is_metadata_key(key: &[u8]) -> bool {
+    key == CONFIG_KEY
+        || key.starts_with(&serialize_prefix(TYPES_PREFIX))
+        || key.starts_with(&serialize_prefix(CF_TYPES_PREFIX))
+}
+
+/// How many pairs bulk writes like `Prefix::migrate_values` write per batch.
+const BULK_BATCH_SIZE: usize = 1000;
+
+struct DBVisitor {
+    db: DB,
+}
+
+impl<'de> Visitor<'de> for DBVisitor {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a database seq")
+    }
+
+    fn visit_seq<A>(self, mut map: A) -> std::result::Result<(), A::Error> 
+        where A: SeqAccess<'de>
+    {
+        while let Some((key, value)) = map.next_element::<(Vec<_>, Vec<_>)>()? {
+            if is_metadata_key(&key) {
+                continue;
+            }
+            self.db.db.put(&key, &value).map_err(|e| serde::de::Error::custom(e))?;
         }
+
+        Ok(())
     }
-}
 
-/// An iterator over the values of a prefix.
-pub struct Values<V> {
-    db_iter: rocksdb::DBRawIterator,
-    prefix: Vec<u8>,
-    _v: PhantomData<V>,
 }
 
-impl<V: DeserializeOwned> Iterator for Values<V> {
-    type Item = Result<V>; // :(
+impl Serialize for DB {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut iter = self.db.raw_iterator();
+        iter.seek_to_first();
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.db_iter.valid() {
-            let v =
-                // We do not reuse the buffer so this is safe
-                unsafe {self.db_iter.key_inner()}
-                    .and_then(|k| if &k[0..self.prefix.len()] == &self.prefix[..] { Some(k) } else { None } )
-                    .and_then(|_|
-                        unsafe {self.db_iter.value_inner()}
-                            .map(|v| Ok(bincode::deserialize(v)?))
-                        );
+        let mut map = serializer.serialize_seq(None)?;
 
-            self.db_iter.next();
-            v
-        } else {
-            None
+        while iter.valid() {
+            if let (Some(key), Some(value)) = (iter.key(), iter.value()) {
+                if !is_metadata_key(&key) {
+                    map.serialize_element(&(key,value))?;
+                }
+            }
+            iter.next();
         }
+
+        map.end()
     }
 }
+
+/// A way to group prefixes.
+#[derive(Clone)]
+pub struct PrefixGroup {
+    db: Arc<rocksdb::DB>,
+    update_lock: Arc<Mutex<()>>,
+    prefix: Vec<u8>,
+    namespaces: Namespaces,
+    config: BincodeConfig,
+    limits: SizeLimits,
+}
+
+impl fmt::Debug for PrefixGroup {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PrefixGroup")
+            .field("prefix", &ByteStr(&self.prefix))
+            .finish()
+    }
+}
+
+impl PrefixGroup {
+    /// Create a prefix inside this prefix group.
+    ///
+    /// See `DB::prefix`
+    pub fn prefix<K: Serialize + DeserializeOwned, V: Serialize + DeserializeOwned>(
+        &self,
+        prefix: &[u8],
+    ) -> Result<Prefix<K, V>> {
+        // No point in using 64bit lenght here
+        // This will never fail
+        let mut prefix_vec = self.prefix.clone();
+        bincode::serialize_into(&mut prefix_vec, &(prefix.len() as u32))?;
+        prefix_vec.extend_from_slice(&prefix);
+        self.namespaces.claim(&prefix_vec, prefix, Namespace::Prefix)?;
+
+        Ok(Prefix {
+            db: self.db.clone(),
+            update_lock: self.update_lock.clone(),
+            prefix: prefix_vec,
+            canonical: false,
+            checksum: false,
+            keys: self.config,
+            values: self.config,
+            limits: self.limits,
+            _k: PhantomData,
+            _v: PhantomData,
+            _c: PhantomData,
+        })
+    }
+
+    /// Create a sub prefix group.
+    ///
+    /// See `DB::prefix_group`
+    pub fn prefix_group(&self, prefix: &[u8]) -> Result<PrefixGroup> {
+        // No point in using 64bit lenght here
+        // This will never fail
+        let mut prefix_vec = self.prefix.clone();
+        bincode::serialize_into(&mut prefix_vec, &(prefix.len() as u32))?;
+        prefix_vec.extend_from_slice(&prefix);
+        self.namespaces.claim(&prefix_vec, prefix, Namespace::Group)?;
+
+        Ok(PrefixGroup {
+            db: self.db.clone(),
+            update_lock: self.update_lock.clone(),
+            prefix: prefix_vec,
+            namespaces: self.namespaces.clone(),
+            config: self.config,
+            limits: self.limits,
+        })
+    }
+
+    /// The names of the prefixes and sub prefix groups of this group that contain any keys, in
+    /// the order of their serialized names.
+    ///
+    /// This skips over the keys of each nested prefix, so it does not visit every key.
+    ///
+    /// This function will return `Err` if the underlying rocksdb command fails.
+    ///
+    /// # Examples
+    /// ```
+    /// # let db = rocksbin::DB::open("db_dir_prefix_names").unwrap();
+    /// let group = db.prefix_group(b"people").unwrap();
+    /// let heights = group.prefix::<String, u64>(b"heights").unwrap();
+    /// let weights = group.prefix::<String, u64>(b"weights").unwrap();
+    ///
+    /// heights.insert("John", &175).unwrap();
+    /// weights.insert("John", &70).unwrap();
+    ///
+    /// assert_eq!(group.prefix_names().unwrap(), vec![b"heights".to_vec(), b"weights".to_vec()]);
+    ///
+    /// group.clear().unwrap();
+    /// assert!(!group.contains_any().unwrap());
+    ///
+    /// # drop(heights);
+    /// # drop(weights);
+    /// # drop(group);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_prefix_names").unwrap();
+    /// ```
+    pub fn prefix_names(&self) -> Result<Vec<Vec<u8>>> {
+        let mut db_iter = self.db.raw_iterator();
+        db_iter.seek(&self.prefix);
+
+        let mut names = Vec::new();
+        while let Some((k, _)) = prefixed_entry(&db_iter, &self.prefix) {
+            // Every nested prefix starts with the length of its name, see `PrefixGroup::prefix`
+            let len = match k.get(..4) {
+                Some(len) => bincode::deserialize::<u32>(len)? as usize,
+                None => {
+                    db_iter.next();
+                    continue;
+                }
+            };
+            let nested = match k.get(..4 + len) {
+                Some(nested) => nested,
+                None => {
+                    db_iter.next();
+                    continue;
+                }
+            };
+            names.push(nested[4..].to_vec());
+
+            // Skip the remaining keys of the nested prefix
+            match successor(&[&self.prefix[..], nested].concat()) {
+                Some(next) => db_iter.seek(&next),
+                None => break,
+            }
+        }
+        Ok(names)
+    }
+
+    /// Whether any prefix of this group, or of its sub prefix groups, contains a key.
+    ///
+    /// This function will return `Err` if the underlying rocksdb command fails.
+    pub fn contains_any(&self) -> Result<bool> {
+        let mut db_iter = self.db.raw_iterator();
+        db_iter.seek(&self.prefix);
+        Ok(prefixed_entry(&db_iter, &self.prefix).is_some())
+    }
+
+    /// Remove every key-value pair of the prefixes of this group, and of its sub prefix groups,
+    /// in one atomic batch.
+    ///
+    /// Like `Prefix::clear` the keys are collected by iterating over the group. Prefixes and
+    /// groups outside this group are never affected, even if their name starts with the name of
+    /// this group, since every name is stored after its length.
+    ///
+    /// This function will return `Err` if the underlying rocksdb command fails.
+    pub fn clear(&self) -> Result<()> {
+        let mut db_iter = self.db.raw_iterator();
+        db_iter.seek(&self.prefix);
+
+        let mut batch = rocksdb::WriteBatch::default();
+
+        while let Some((k, _)) = prefixed_entry(&db_iter, &self.prefix) {
+            batch.delete(&[&self.prefix[..], k].concat())?;
+            db_iter.next();
+        }
+
+        self.db.write(batch)?;
+        Ok(())
+    }
+}
+
+/// A grouping of data in a database.
+///
+/// Most methods of `Prefix` use `Borrow` in a similar fashion like `HashMap`.
+/// This means that if you have a prefix of type `Prefix<String, u64>` you can use both `&String`
+/// and `&str` to access the data.
+///
+/// Values are serialized with bincode unless another `Codec` is given as `C`.
+#[derive(Clone)]
+pub struct Prefix<K, V, C = Bincode> {
+    db: Arc<rocksdb::DB>,
+    update_lock: Arc<Mutex<()>>,
+    prefix: Vec<u8>,
+    canonical: bool,
+    checksum: bool,
+    values: BincodeConfig,
+    keys: BincodeConfig,
+    limits: SizeLimits,
+    _k: PhantomData<K>,
+    _v: PhantomData<V>,
+    _c: PhantomData<C>,
+}
+
+impl<K, V, C> fmt::Debug for Prefix<K, V, C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Prefix")
+            .field("prefix", &ByteStr(&self.prefix))
+            .field("key", &type_name::<K>())
+            .field("value", &type_name::<V>())
+            .field("codec", &type_name::<C>())
+            .finish()
+    }
+}
+
+// Functions that only need the key type, so they can be used with any value type
+impl<K: Serialize + DeserializeOwned, V, C> Prefix<K, V, C> {
+    /// Check if this prefix contains a key.
+    ///
+    /// The value is not deserialized, so the value type does not have to be deserializable. The
+    /// rocksdb bindings have no `key_may_exist`, so the value is still read.
+    ///
+    /// This function will return `Err` if one of the following occures:
+    /// - Serializing the key fails
+    /// - The underlying rocksdb command fails
+    pub fn contains_key<Q>(&self, key: &Q) -> Result<bool>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let key_buf = self.key_buf(key)?;
+        Ok(self.db.get(&key_buf)?.is_some())
+    }
+
+    /// The full rocksdb key of `key`, i.e., the prefix followed by the serialized key.
+    fn key_buf<Q>(&self, key: &Q) -> Result<Vec<u8>>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let size = self.keys.serialized_size(&key)? as usize;
+        self.limits.check_key(size)?;
+
+        let mut key_buf = self.prefix.clone();
+        key_buf.reserve(size);
+        encode_key(&mut key_buf, key, self.keys)?;
+        Ok(key_buf)
+    }
+}
+
+impl<K: Serialize + DeserializeOwned, V: Serialize + DeserializeOwned, C: Codec> Prefix<K, V, C> {
+    /// The raw bytes every key of this prefix starts with.
+    ///
+    /// A key is stored in rocksdb as these bytes followed by the bincode serialized key, which
+    /// is useful if you need to access the same data with rocksdb directly.
+    pub fn prefix_bytes(&self) -> &[u8] {
+        &self.prefix
+    }
+
+    /// Serialize values canonically, see the `canonical` module.
+    ///
+    /// Equal values then always serialize to the same bytes, even if they contain maps like
+    /// `HashMap`, at the cost of buffering and sorting the entries of every map on insert.
+    /// Canonical values are always serialized with bincode and its default configuration, even if
+    /// the prefix has another `Codec` or the database another `BincodeConfig`.
+    pub fn canonical(mut self) -> Prefix<K, V, C> {
+        self.canonical = true;
+        self.values = BincodeConfig::default();
+        self
+    }
+
+    /// Put an in-process cache of deserialized values in front of this prefix.
+    ///
+    /// See `CachedPrefix`
+    pub fn cached(self) -> CachedPrefix<K, V, C>
+    where
+        V: Clone,
+    {
+        CachedPrefix::new(self)
+    }
+
+    /// Run the functions of this prefix on the blocking thread pool of tokio, for use from async
+    /// code. Requires the `async` feature.
+    ///
+    /// See `AsyncPrefix`
+    #[cfg(feature = "async")]
+    pub fn into_async(self) -> AsyncPrefix<K, V, C>
+    where
+        K: Send + Sync + 'static,
+        V: Send + Sync + 'static,
+        C: Send + Sync + 'static,
+    {
+        AsyncPrefix::new(self)
+    }
+
+    /// Maintain a secondary index of this prefix in `index`, using `extract` to compute the index
+    /// key of each key-value pair.
+    ///
+    /// The prefix and the index are written in one batch, so `index` has to be a prefix of the
+    /// same database, otherwise `ErrorKind::ForeignPrefix` is returned.
+    ///
+    /// See `Index`
+    pub fn indexed<I, F>(self, index: Prefix<I, K>, extract: F) -> Result<Index<K, V, I, C>>
+    where
+        I: Serialize + DeserializeOwned,
+        F: Fn(&K, &V) -> I + Send + Sync + 'static,
+    {
+        if !Arc::ptr_eq(&self.db, &index.db) {
+            return Err(Box::new(ErrorKind::ForeignPrefix));
+        }
+        Ok(Index::new(self, index, extract))
+    }
+
+    /// Store a checksum together with every value, which is verified whenever the value is read.
+    ///
+    /// If a stored value does not match its checksum, reading it returns
+    /// `ErrorKind::Corruption`. This catches values that were silently corrupted after being
+    /// written, at the cost of four extra bytes per value. A prefix must always be used with the
+    /// same checksum setting, values written without a checksum can not be read with one.
+    pub fn checksummed(mut self) -> Prefix<K, V, C> {
+        self.checksum = true;
+        self
+    }
+
+    /// Serialize keys so that their byte order matches their logical order, making iteration
+    /// return them sorted.
+    ///
+    /// By default keys are serialized with bincode, which stores integers in little endian, so
+    /// `256` comes before `1` when iterating. Ordered prefixes store integers in big endian
+    /// instead, which orders unsigned integers and tuples and structs of them correctly. Signed
+    /// integers still sort negative numbers after positive ones, and strings and other
+    /// variable-length keys are sorted by length first. A prefix must always be used with the
+    /// same setting, keys written by one can not be read by the other. Ordered keys ignore the
+    /// `BincodeConfig` of the database.
+    ///
+    /// # Examples
+    /// ```
+    /// # let db = rocksbin::DB::open("db_dir_ordered").unwrap();
+    /// let squares = db.prefix::<u64, u64>(b"squares").unwrap().ordered();
+    ///
+    /// for &n in &[256, 1, 70000, 2] {
+    ///     squares.insert(&n, &(n * n)).unwrap();
+    /// }
+    ///
+    /// let keys = squares.keys().collect::<Result<Vec<_>, _>>().unwrap();
+    /// assert_eq!(keys, vec![1, 2, 256, 70000]);
+    ///
+    /// # drop(squares);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_ordered").unwrap();
+    /// ```
+    pub fn ordered(mut self) -> Prefix<K, V, C> {
+        self.keys = codec::ORDERED;
+        self
+    }
+
+    /// The serialized form of a value.
+    fn value_buf<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>> {
+        let mut value_buf = if self.canonical {
+            canonical::serialize(value)?
+        } else {
+            C::serialize_with(value, self.values)?
+        };
+
+        self.limits.check_value(value_buf.len())?;
+
+        if self.checksum {
+            let checksum = crc32(&value_buf);
+            value_buf.extend_from_slice(&checksum.to_le_bytes());
+        }
+        Ok(value_buf)
+    }
+
+    /// Returns the value coresponing to the key. If there is no such value, `Ok(None)` is returned.
+    ///
+    /// The value is deserialized straight from the buffer rocksdb returns, without copying it
+    /// first.
+    ///
+    /// This function will return `Err` if one of the following occures:
+    /// - Serializing the key fails
+    /// - The underlying rocksdb command fails
+    /// - Deserializing of the value fails
+    ///
+    /// # Examples
+    /// ```
+    /// # let db = rocksbin::DB::open("db_dir2").unwrap();
+    /// let heights = db.prefix::<String, u64>(b"heights").unwrap();
+    ///
+    /// heights.insert("John", &175).unwrap();
+    /// heights.insert(&"Lisa".to_string(), &165).unwrap();
+    ///
+    /// assert_eq!(heights.get("John").unwrap(), Some(175));
+    /// assert_eq!(heights.get("Lisa").unwrap(), Some(165));
+    ///
+    /// # drop(heights);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir2").unwrap();
+    /// ```
+    pub fn get<Q>(&self, key: &Q) -> Result<Option<V>>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let key_buf = self.key_buf(key)?;
+        self.get_buf(&key_buf)
+    }
+
+    /// Returns the entry of the key, holding its value if it has one, see `Entry`.
+    ///
+    /// The key is only serialized once, here, and reused by the writes of the entry.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::get`
+    pub fn entry<Q>(&self, key: &Q) -> Result<Entry<'_, K, V, C>>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let key_buf = self.key_buf(key)?;
+        match self.get_buf(&key_buf)? {
+            Some(value) => Ok(Entry::Occupied(OccupiedEntry::new(self, key_buf, value))),
+            None => Ok(Entry::Vacant(VacantEntry::new(self, key_buf))),
+        }
+    }
+
+    /// Returns the value stored under an already serialized key.
+    fn get_buf(&self, key_buf: &[u8]) -> Result<Option<V>> {
+        match self.db.get(key_buf)? {
+            Some(data) => Ok(Some(decode_value::<C, _>(key_buf, &data, self.checksum, self.values)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Look up many keys at once, returning the values in the same order as `keys`.
+    ///
+    /// The rocksdb bindings have no batched lookup, so the keys are still looked up one by one,
+    /// but all of them are read from the same snapshot of the database.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::get`
+    ///
+    /// # Examples
+    /// ```
+    /// # let db = rocksbin::DB::open("db_dir_multi_get").unwrap();
+    /// let heights = db.prefix::<String, u64>(b"heights").unwrap();
+    ///
+    /// heights.insert("John", &175).unwrap();
+    /// heights.insert("Lisa", &165).unwrap();
+    ///
+    /// assert_eq!(heights.multi_get(&["Lisa", "Kari", "John"]).unwrap(), vec![Some(165), None, Some(175)]);
+    ///
+    /// # drop(heights);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_multi_get").unwrap();
+    /// ```
+    pub fn multi_get<Q>(&self, keys: &[&Q]) -> Result<Vec<Option<V>>>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let snapshot = self.db.snapshot();
+
+        keys.iter()
+            .map(|key| {
+                let key_buf = self.key_buf(*key)?;
+                match snapshot.get(&key_buf)? {
+                    Some(data) => Ok(Some(decode_value::<C, _>(&key_buf, &data, self.checksum, self.values)?)),
+                    None => Ok(None),
+                }
+            })
+            .collect()
+    }
+
+    /// Look up many keys and split them into the ones that are present, together with their
+    /// values, and the ones that are missing.
+    ///
+    /// Both lists keep the order of `keys`.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::get`
+    #[allow(clippy::type_complexity)]
+    pub fn partition_get<Q>(&self, keys: &[Q]) -> Result<(Vec<(Q, V)>, Vec<Q>)>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + Clone,
+    {
+        let mut present = Vec::new();
+        let mut missing = Vec::new();
+
+        for key in keys {
+            match self.get(key)? {
+                Some(value) => present.push((key.clone(), value)),
+                None => missing.push(key.clone()),
+            }
+        }
+
+        Ok((present, missing))
+    }
+
+    /// Returns the value coresponing to the key, deserialized as either `V` or `V2`.
+    ///
+    /// The value is first deserialized as `V`, and only if that fails, or does not use up all of
+    /// the stored bytes, as `V2`. This is useful while migrating a prefix from values of type
+    /// `V2` to values of type `V`.
+    ///
+    /// This function will return `Err` if one of the following occures:
+    /// - Serializing the key fails
+    /// - The underlying rocksdb command fails
+    /// - Deserializing the value as `V2` fails after deserializing it as `V` failed
+    ///
+    /// # Examples
+    /// ```
+    /// use rocksbin::Either;
+    ///
+    /// # let db = rocksbin::DB::open("db_dir_get_either").unwrap();
+    /// let old_heights = db.prefix::<String, u32>(b"heights").unwrap();
+    /// old_heights.insert("John", &175).unwrap();
+    ///
+    /// let heights = db.prefix::<String, (u32, String)>(b"heights").unwrap();
+    /// heights.insert("Lisa", &(165, "cm".to_string())).unwrap();
+    ///
+    /// assert_eq!(heights.get_either::<u32, _>("John").unwrap(), Some(Either::Right(175)));
+    /// assert_eq!(
+    ///     heights.get_either::<u32, _>("Lisa").unwrap(),
+    ///     Some(Either::Left((165, "cm".to_string())))
+    /// );
+    ///
+    /// # drop(old_heights);
+    /// # drop(heights);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_get_either").unwrap();
+    /// ```
+    pub fn get_either<V2, Q>(&self, key: &Q) -> Result<Option<Either<V, V2>>>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+        V2: DeserializeOwned,
+    {
+        let key_buf = self.key_buf(key)?;
+        let data = match self.db.get(&key_buf)? {
+            Some(data) => data,
+            None => return Ok(None),
+        };
+        let value_buf = strip_checksum(&key_buf, &data, self.checksum)?;
+
+        if let Ok(value) = C::deserialize_with::<V>(value_buf, self.values) {
+            if C::serialize_with(&value, self.values)?.len() == value_buf.len() {
+                return Ok(Some(Either::Left(value)));
+            }
+        }
+
+        Ok(Some(Either::Right(C::deserialize_with(value_buf, self.values)?)))
+    }
+
+    /// Returns the size in bytes of the serialized value coresponing to the key, without
+    /// deserializing it. If there is no such value, `Ok(None)` is returned.
+    ///
+    /// The checksum of a checksummed prefix is not counted, see `Prefix::checksummed`.
+    ///
+    /// This function will return `Err` if one of the following occures:
+    /// - Serializing the key fails
+    /// - The value does not match its checksum
+    /// - The underlying rocksdb command fails
+    pub fn value_size<Q>(&self, key: &Q) -> Result<Option<usize>>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let key_buf = self.key_buf(key)?;
+        match self.db.get(&key_buf)? {
+            Some(data) => Ok(Some(strip_checksum(&key_buf, &data, self.checksum)?.len())),
+            None => Ok(None),
+        }
+    }
+
+    /// Insert a key-value pair.
+    ///
+    /// This function will return `Err` if one of the following occures:
+    /// - Serializing the key or the value fails
+    /// - The underlying rocksdb command fails
+    pub fn insert<Q>(&self, key: &Q, value: &V) -> Result<()>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let key_buf = self.key_buf(key)?;
+        let value_buf = self.value_buf(value)?;
+
+        self.db.put(&key_buf, &value_buf)?;
+        Ok(())
+    }
+
+    /// Insert a key-value pair and wait until the write is synced to disk.
+    ///
+    /// See `DB::write_sync`
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::insert`
+    pub fn insert_sync<Q>(&self, key: &Q, value: &V) -> Result<()>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let key_buf = self.key_buf(key)?;
+        let value_buf = self.value_buf(value)?;
+
+        let mut opts = rocksdb::WriteOptions::default();
+        opts.set_sync(true);
+
+        self.db.put_opt(&key_buf, &value_buf, &opts)?;
+        Ok(())
+    }
+
+    /// Merge an operand into the value of a key, using the merge function of the prefix.
+    ///
+    /// This only works for prefixes with merge functions, see `MergeOperators`. The merge
+    /// functions read values as they are serialized by the database, so checksummed and
+    /// canonical prefixes can not be merged into.
+    ///
+    /// This function will return `Err` if one of the following occures:
+    /// - The prefix has no merge functions, or is checksummed or canonical
+    /// - Serializing the key or the operand fails
+    /// - The underlying rocksdb command fails
+    pub fn merge<Q>(&self, key: &Q, operand: &V) -> Result<()>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        if self.checksum || self.canonical || merge::value_type(&self.db, &self.prefix).is_none() {
+            return Err(Box::new(ErrorKind::MergeNotSupported {
+                prefix: self.prefix.clone(),
+            }));
+        }
+
+        let key_buf = self.key_buf(key)?;
+        let operand_buf = self.value_buf(operand)?;
+
+        self.db.merge(&key_buf, &operand_buf)?;
+        Ok(())
+    }
+
+    /// Removes a key-value pair.
+    ///
+    /// This function will return `Err` if one of the following occures:
+    /// - Serializing the key fails
+    /// - The underlying rocksdb command fails
+    pub fn remove<Q>(&self, key: &Q) -> Result<()>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let key_buf = self.key_buf(key)?;
+
+        self.db.delete(&key_buf)?;
+        Ok(())
+    }
+
+    /// Insert many key-value pairs in one atomic batch.
+    ///
+    /// This function will return `Err` if one of the following occures:
+    /// - Serializing one of the keys or values fails, in which case nothing is inserted
+    /// - The underlying rocksdb command fails
+    pub fn extend<I: IntoIterator<Item = (K, V)>>(&self, iter: I) -> Result<()> {
+        let mut batch = rocksdb::WriteBatch::default();
+
+        for (key, value) in iter {
+            batch.put(&self.key_buf(&key)?, &self.value_buf(&value)?)?;
+        }
+
+        self.db.write(batch)?;
+        Ok(())
+    }
+
+    /// Removes many key-value pairs in one atomic batch.
+    ///
+    /// Returns the number of keys that were removed, including keys that did not exist.
+    ///
+    /// This function will return `Err` if one of the following occures:
+    /// - Serializing one of the keys fails, in which case nothing is removed
+    /// - The underlying rocksdb command fails
+    pub fn remove_many<'a, Q, I>(&self, keys: I) -> Result<u64>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized + 'a,
+        I: IntoIterator<Item = &'a Q>,
+    {
+        let mut batch = rocksdb::WriteBatch::default();
+        let mut count = 0;
+
+        for key in keys {
+            batch.delete(&self.key_buf(key)?)?;
+            count += 1;
+        }
+
+        self.db.write(batch)?;
+        Ok(count)
+    }
+
+    /// Remove every key-value pair for which `f` returns `false`, in one atomic batch.
+    ///
+    /// The removals are collected while iterating and written once the iteration is done. This
+    /// is atomic with respect to functions like [`Prefix::update_retry`], so `f` must not call
+    /// them or it deadlocks. A pair written by `Prefix::insert` in the meantime may still be
+    /// removed.
+    ///
+    /// This function will return `Err` if one of the following occures:
+    /// - Deserializing one of the keys or values fails, in which case nothing is removed
+    /// - The underlying rocksdb command fails
+    pub fn retain<F: FnMut(&K, &V) -> bool>(&self, mut f: F) -> Result<()> {
+        let _guard = self.update_lock.lock().unwrap();
+        let mut db_iter = self.db.raw_iterator();
+        db_iter.seek(&self.prefix);
+
+        let mut batch = rocksdb::WriteBatch::default();
+
+        while let Some((k, v)) = prefixed_entry(&db_iter, &self.prefix) {
+            let key_buf = [&self.prefix[..], k].concat();
+            let key = decode_key(k, self.keys)?;
+            let value = decode_value::<C, _>(&key_buf, v, self.checksum, self.values)?;
+            if !f(&key, &value) {
+                batch.delete(&key_buf)?;
+            }
+            db_iter.next();
+        }
+
+        self.db.write(batch)?;
+        Ok(())
+    }
+
+    /// Apply `f` to every value of this prefix and write the changed values back in one atomic
+    /// batch.
+    ///
+    /// A value counts as changed if it serializes differently after `f` was applied, so values
+    /// `f` leaves alone are not written again. The writes are collected while iterating and
+    /// written once the iteration is done. This is atomic with respect to functions like
+    /// [`Prefix::update_retry`], so `f` must not call them or it deadlocks. A value written by
+    /// `Prefix::insert` in the meantime may still be overwritten.
+    ///
+    /// This function will return `Err` if one of the following occures:
+    /// - Deserializing one of the keys or values, or serializing one of the changed values fails,
+    ///   in which case nothing is written
+    /// - The underlying rocksdb command fails
+    ///
+    /// # Examples
+    /// ```
+    /// # let db = rocksbin::DB::open("db_dir_modify_all").unwrap();
+    /// let prices = db.prefix::<String, u64>(b"prices").unwrap();
+    ///
+    /// prices.insert("apple", &10).unwrap();
+    /// prices.insert("pear", &12).unwrap();
+    ///
+    /// prices.modify_all(|_, price| *price += 2).unwrap();
+    ///
+    /// assert_eq!(prices.get("apple").unwrap(), Some(12));
+    /// assert_eq!(prices.get("pear").unwrap(), Some(14));
+    ///
+    /// # drop(prices);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_modify_all").unwrap();
+    /// ```
+    pub fn modify_all<F: FnMut(&K, &mut V)>(&self, mut f: F) -> Result<()> {
+        let _guard = self.update_lock.lock().unwrap();
+        let mut db_iter = self.db.raw_iterator();
+        db_iter.seek(&self.prefix);
+
+        let mut batch = rocksdb::WriteBatch::default();
+
+        while let Some((k, v)) = prefixed_entry(&db_iter, &self.prefix) {
+            let key_buf = [&self.prefix[..], k].concat();
+            let key = decode_key(k, self.keys)?;
+            let mut value = decode_value::<C, _>(&key_buf, v, self.checksum, self.values)?;
+            f(&key, &mut value);
+
+            let value_buf = self.value_buf(&value)?;
+            if value_buf != v {
+                batch.put(&key_buf, &value_buf)?;
+            }
+            db_iter.next();
+        }
+
+        self.db.write(batch)?;
+        Ok(())
+    }
+
+    /// Read every value of this prefix, convert it with `f` and write it under the same key into
+    /// `new`, e.g., to move the values to a new type after an incompatible schema change.
+    ///
+    /// The pairs are written in batches of 1000, so if an error occures some of them may already
+    /// be in `new`. This prefix is left as it is, use `Prefix::clear` to remove the old values once
+    /// the migration succeeded.
+    ///
+    /// This function will return `Err` if one of the following occures:
+    /// - Deserializing one of the keys or values, or serializing one of the new keys or values
+    ///   fails
+    /// - The underlying rocksdb command fails
+    ///
+    /// # Examples
+    /// ```
+    /// # let db = rocksbin::DB::open("db_dir_migrate_values").unwrap();
+    /// let heights = db.prefix::<String, u32>(b"heights").unwrap();
+    /// let heights_v2 = db.prefix::<String, (u32, String)>(b"heights_v2").unwrap();
+    ///
+    /// heights.insert("John", &175).unwrap();
+    ///
+    /// heights.migrate_values(&heights_v2, |height| (height, "cm".to_string())).unwrap();
+    ///
+    /// assert_eq!(heights_v2.get("John").unwrap(), Some((175, "cm".to_string())));
+    ///
+    /// # drop(heights);
+    /// # drop(heights_v2);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_migrate_values").unwrap();
+    /// ```
+    pub fn migrate_values<NewV, NewC, F>(&self, new: &Prefix<K, NewV, NewC>, f: F) -> Result<()>
+    where
+        NewV: Serialize + DeserializeOwned,
+        NewC: Codec,
+        F: Fn(V) -> NewV,
+    {
+        let mut db_iter = self.db.raw_iterator();
+        db_iter.seek(&self.prefix);
+
+        let mut batch = rocksdb::WriteBatch::default();
+
+        while let Some((k, v)) = prefixed_entry(&db_iter, &self.prefix) {
+            let key_buf = [&self.prefix[..], k].concat();
+            let key: K = decode_key(k, self.keys)?;
+            let value = decode_value::<C, _>(&key_buf, v, self.checksum, self.values)?;
+
+            batch.put(&new.key_buf(&key)?, &new.value_buf(&f(value))?)?;
+            if batch.len() >= BULK_BATCH_SIZE {
+                new.db.write(mem::take(&mut batch))?;
+            }
+            db_iter.next();
+        }
+
+        new.db.write(batch)?;
+        Ok(())
+    }
+
+    /// Insert many key-value pairs that are sorted by key, faster than inserting them one by one.
+    ///
+    /// The pairs must be sorted by their serialized keys, the order `Prefix::iter` visits them
+    /// in, and every key must be unique. For a `Prefix::ordered` prefix this is the order of `K`.
+    /// The pairs are written in batches of 1000, so if an error occures the pairs before it may
+    /// already be inserted.
+    ///
+    /// This function will return `Err` if one of the following occures:
+    /// - A key is not ordered after the key before it, which returns `ErrorKind::Unsorted`
+    /// - Serializing one of the keys or values fails
+    /// - The underlying rocksdb command fails
+    ///
+    /// # Examples
+    /// ```
+    /// # let db = rocksbin::DB::open("db_dir_ingest_sorted").unwrap();
+    /// let squares = db.prefix::<u64, u64>(b"squares").unwrap().ordered();
+    ///
+    /// squares.ingest_sorted((0..10_000).map(|n| (n, n * n))).unwrap();
+    ///
+    /// assert_eq!(squares.get(&12).unwrap(), Some(144));
+    ///
+    /// # drop(squares);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_ingest_sorted").unwrap();
+    /// ```
+    pub fn ingest_sorted<I: IntoIterator<Item = (K, V)>>(&self, sorted: I) -> Result<()> {
+        let mut batch = rocksdb::WriteBatch::default();
+        let mut last_key: Option<Vec<u8>> = None;
+
+        for (key, value) in sorted {
+            let key_buf = self.key_buf(&key)?;
+            if let Some(ref last_key) = last_key {
+                if key_buf <= *last_key {
+                    return Err(Box::new(ErrorKind::Unsorted { key: key_buf }));
+                }
+            }
+
+            batch.put(&key_buf, &self.value_buf(&value)?)?;
+            if batch.len() >= BULK_BATCH_SIZE {
+                self.db.write(mem::take(&mut batch))?;
+            }
+            last_key = Some(key_buf);
+        }
+
+        self.db.write(batch)?;
+        Ok(())
+    }
+
+    /// Remove every key-value pair of this prefix in one atomic batch and return them.
+    ///
+    /// This is atomic with respect to functions like [`Prefix::update_retry`]. A pair inserted by
+    /// `Prefix::insert` while draining may be removed without being returned.
+    ///
+    /// This function will return `Err` if one of the following occures:
+    /// - Deserializing one of the keys or values fails, in which case nothing is removed
+    /// - The underlying rocksdb command fails
+    ///
+    /// # Examples
+    /// ```
+    /// # let db = rocksbin::DB::open("db_dir_drain").unwrap();
+    /// let jobs = db.prefix::<u64, String>(b"jobs").unwrap();
+    ///
+    /// jobs.insert(&1, &"send mail".to_string()).unwrap();
+    /// jobs.insert(&2, &"clean up".to_string()).unwrap();
+    ///
+    /// let drained = jobs.drain().unwrap();
+    /// assert_eq!(drained, vec![(1, "send mail".to_string()), (2, "clean up".to_string())]);
+    /// assert!(jobs.is_empty().unwrap());
+    ///
+    /// # drop(jobs);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_drain").unwrap();
+    /// ```
+    pub fn drain(&self) -> Result<Vec<(K, V)>> {
+        self.drain_bufs(&self.prefix, None)
+    }
+
+    /// Remove the key-value pairs of this prefix whose keys are in `range` in one atomic batch
+    /// and return them.
+    ///
+    /// Keys are ordered like in `Prefix::range`.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::drain`, or if serializing one
+    /// of the bounds fails.
+    pub fn drain_range<Q, R>(&self, range: R) -> Result<Vec<(K, V)>>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let (start, end) = self.range_bufs(range)?;
+        self.drain_bufs(&start, end)
+    }
+
+    fn drain_bufs(&self, start: &[u8], end: Option<Vec<u8>>) -> Result<Vec<(K, V)>> {
+        let _guard = self.update_lock.lock().unwrap();
+        let mut raw = RawIter::new(Source::DB(self.db.clone()), self.prefix.clone(), start, end);
+
+        let mut batch = rocksdb::WriteBatch::default();
+        let mut pairs = Vec::new();
+
+        while let Some((key_buf, value_buf)) = raw.front() {
+            let key = decode_key(&key_buf[self.prefix.len()..], self.keys)?;
+            let value = decode_value::<C, _>(key_buf, value_buf, self.checksum, self.values)?;
+            batch.delete(key_buf)?;
+            pairs.push((key, value));
+            raw.next();
+        }
+
+        self.db.write(batch)?;
+        Ok(pairs)
+    }
+
+    /// Remove every key-value pair of this prefix in one atomic batch.
+    ///
+    /// The rocksdb bindings have no range deletion, so the keys are collected by iterating over
+    /// the prefix. Other prefixes are never affected, even if their name starts with the name of
+    /// this prefix.
+    ///
+    /// This function will return `Err` if the underlying rocksdb command fails.
+    pub fn clear(&self) -> Result<()> {
+        let mut db_iter = self.db.raw_iterator();
+        db_iter.seek(&self.prefix);
+
+        let mut batch = rocksdb::WriteBatch::default();
+
+        while let Some((k, _)) = prefixed_entry(&db_iter, &self.prefix) {
+            batch.delete(&[&self.prefix[..], k].concat())?;
+            db_iter.next();
+        }
+
+        self.db.write(batch)?;
+        Ok(())
+    }
+
+    /// Remove every key-value pair whose key is ordered before `cutoff`, then compact that key
+    /// range so the space is reclaimed right away.
+    ///
+    /// Keys are ordered by their serialized bytes, the same order as `Prefix::iter` visits them.
+    /// Returns the number of removed pairs.
+    ///
+    /// This function will return `Err` if one of the following occures:
+    /// - Serializing the cutoff fails
+    /// - The underlying rocksdb command fails
+    pub fn expire_before<Q>(&self, cutoff: &Q) -> Result<u64>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let cutoff_buf = self.key_buf(cutoff)?;
+        let mut db_iter = self.db.raw_iterator();
+        db_iter.seek(&self.prefix);
+
+        let mut batch = rocksdb::WriteBatch::default();
+        let mut count = 0;
+
+        while let Some((k, _)) = prefixed_entry(&db_iter, &self.prefix) {
+            let key = [&self.prefix[..], k].concat();
+            if key >= cutoff_buf {
+                break;
+            }
+            batch.delete(&key)?;
+            count += 1;
+            db_iter.next();
+        }
+
+        self.db.write(batch)?;
+        self.db.compact_range(Some(&self.prefix), Some(&cutoff_buf));
+        Ok(count)
+    }
+
+    /// Returns the number of key-value pairs in this prefix.
+    ///
+    /// This visits every key, but neither keys nor values are deserialized.
+    pub fn len(&self) -> Result<usize> {
+        let mut db_iter = self.db.raw_iterator();
+        db_iter.seek(&self.prefix);
+
+        let mut count = 0;
+        while prefixed_entry(&db_iter, &self.prefix).is_some() {
+            count += 1;
+            db_iter.next();
+        }
+        Ok(count)
+    }
+
+    /// Returns the number of bytes the key-value pairs of this prefix take up, before
+    /// compression.
+    ///
+    /// This is the exact sum of the lengths of every stored key and value, found by a full scan
+    /// of the prefix, so it takes time proportional to the number of pairs like `len`. The rocksdb
+    /// bindings have no way to estimate the size of a key range cheaply. It does not include the
+    /// overhead of rocksdb, nor removed or overwritten entries that are not compacted yet.
+    ///
+    /// This function will return `Err` if the underlying rocksdb command fails.
+    pub fn stored_size(&self) -> Result<u64> {
+        let mut db_iter = self.db.raw_iterator();
+        db_iter.seek(&self.prefix);
+
+        let mut size = 0;
+        while let Some((k, v)) = prefixed_entry(&db_iter, &self.prefix) {
+            size += (self.prefix.len() + k.len() + v.len()) as u64;
+            db_iter.next();
+        }
+        Ok(size)
+    }
+
+    /// Returns `true` if this prefix contains no key-value pairs.
+    pub fn is_empty(&self) -> Result<bool> {
+        let mut db_iter = self.db.raw_iterator();
+        db_iter.seek(&self.prefix);
+
+        Ok(prefixed_entry(&db_iter, &self.prefix).is_none())
+    }
+
+    /// Modify a value coresponing to a key.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::get` and `Prefix::insert`
+    pub fn modify<Q, F: FnOnce(&mut V)>(&self, key: &Q, f: F) -> Result<()>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let key_buf = self.key_buf(key)?;
+
+        if let Some(mut value) = self.get_buf(&key_buf)? {
+            f(&mut value);
+            self.db.put(&key_buf, &self.value_buf(&value)?)?;
+        }
+        Ok(())
+    }
+
+    /// Modify the value coresponing to a key, or `default` if there is none, and store the
+    /// result.
+    ///
+    /// Unlike `Prefix::modify` this always writes the key.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::get` and `Prefix::insert`
+    ///
+    /// # Examples
+    /// ```
+    /// # let db = rocksbin::DB::open("db_dir_modify_or_insert").unwrap();
+    /// let counts = db.prefix::<String, u64>(b"counts").unwrap();
+    ///
+    /// counts.modify_or_insert("salmon", 0, |count| *count += 1).unwrap();
+    /// counts.modify_or_insert("salmon", 0, |count| *count += 1).unwrap();
+    ///
+    /// assert_eq!(counts.get("salmon").unwrap(), Some(2));
+    ///
+    /// # drop(counts);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_modify_or_insert").unwrap();
+    /// ```
+    pub fn modify_or_insert<Q, F: FnOnce(&mut V)>(&self, key: &Q, default: V, f: F) -> Result<()>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let key_buf = self.key_buf(key)?;
+
+        let mut value = self.get_buf(&key_buf)?.unwrap_or(default);
+        f(&mut value);
+        self.db.put(&key_buf, &self.value_buf(&value)?)?;
+        Ok(())
+    }
+
+    /// Returns the value coresponing to a key, or inserts and returns the value computed by `f`
+    /// if there is none.
+    ///
+    /// Concurrent calls for a missing key are serialized, so `f` only runs in one of them and the
+    /// others return the value it computed. Plain writes like `insert` are not coordinated with
+    /// it.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::get` and `Prefix::insert`
+    ///
+    /// # Examples
+    /// ```
+    /// # let db = rocksbin::DB::open("db_dir_get_or_insert_with").unwrap();
+    /// let heights = db.prefix::<String, u64>(b"heights").unwrap();
+    ///
+    /// assert_eq!(heights.get_or_insert_with("John", || 175).unwrap(), 175);
+    /// assert_eq!(heights.get_or_insert_with("John", || 180).unwrap(), 175);
+    ///
+    /// # drop(heights);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_get_or_insert_with").unwrap();
+    /// ```
+    pub fn get_or_insert_with<Q, F: FnOnce() -> V>(&self, key: &Q, f: F) -> Result<V>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let key_buf = self.key_buf(key)?;
+
+        if let Some(value) = self.get_buf(&key_buf)? {
+            return Ok(value);
+        }
+
+        let _guard = self.update_lock.lock().unwrap();
+        if let Some(value) = self.get_buf(&key_buf)? {
+            return Ok(value);
+        }
+
+        let value = f();
+        self.db.put(&key_buf, &self.value_buf(&value)?)?;
+        Ok(value)
+    }
+
+    /// Atomically update the value coresponing to a key.
+    ///
+    /// `f` is given the current value and returns the new value, or `None` to remove the key,
+    /// together with a result that is returned once the update is written. The update is
+    /// optimistic: `f` runs without any lock held, and if another `update_retry` on this database
+    /// changed the value in the meantime, `f` is run again with the new value. `f` should
+    /// therefore not have side effects.
+    ///
+    /// Only other calls to `update_retry` are detected as conflicts, plain writes like `insert`
+    /// are not coordinated with it.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::get` and `Prefix::insert`
+    ///
+    /// # Examples
+    /// ```
+    /// # let db = rocksbin::DB::open("db_dir_update_retry").unwrap();
+    /// let counts = db.prefix::<String, u64>(b"counts").unwrap();
+    ///
+    /// let old = counts.update_retry("salmon", |count| {
+    ///     (Some(count.unwrap_or(0) + 1), count)
+    /// }).unwrap();
+    ///
+    /// assert_eq!(old, None);
+    /// assert_eq!(counts.get("salmon").unwrap(), Some(1));
+    ///
+    /// # drop(counts);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_update_retry").unwrap();
+    /// ```
+    pub fn update_retry<Q, T, F>(&self, key: &Q, mut f: F) -> Result<T>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+        F: FnMut(Option<V>) -> (Option<V>, T),
+    {
+        let key_buf = self.key_buf(key)?;
+
+        loop {
+            let current = self.db.get(&key_buf)?.map(|data| data.to_vec());
+            let old_value = match current {
+                Some(ref data) => Some(decode_value::<C, _>(&key_buf, data, self.checksum, self.values)?),
+                None => None,
+            };
+
+            let (new_value, result) = f(old_value);
+            let value_buf = match new_value {
+                Some(ref value) => Some(self.value_buf(value)?),
+                None => None,
+            };
+
+            let _guard = self.update_lock.lock().unwrap();
+            if self.db.get(&key_buf)?.map(|data| data.to_vec()) != current {
+                continue;
+            }
+
+            match value_buf {
+                Some(value_buf) => self.db.put(&key_buf, &value_buf)?,
+                None => self.db.delete(&key_buf)?,
+            }
+            return Ok(result);
+        }
+    }
+
+    /// Insert a key-value pair and return the previous value, as one atomic operation.
+    ///
+    /// No other `swap` or `update_retry` on this database can write the key between reading the
+    /// previous value and writing the new one.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::get` and `Prefix::insert`
+    pub fn swap<Q>(&self, key: &Q, value: &V) -> Result<Option<V>>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let key_buf = self.key_buf(key)?;
+        let value_buf = self.value_buf(value)?;
+
+        let _guard = self.update_lock.lock().unwrap();
+        let old_value = match self.db.get(&key_buf)? {
+            Some(data) => Some(decode_value::<C, _>(&key_buf, &data, self.checksum, self.values)?),
+            None => None,
+        };
+
+        self.db.put(&key_buf, &value_buf)?;
+        Ok(old_value)
+    }
+
+    /// Set the value of a key to `new`, or remove it if `new` is `None`, but only if its current
+    /// value is `expected`, where `None` means that the key is missing.
+    ///
+    /// Returns `true` if the value was swapped. Like `Prefix::swap` this is atomic with respect
+    /// to other `swap`, `update_retry` and `compare_and_swap` calls on this database.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::get` and `Prefix::insert`
+    ///
+    /// # Examples
+    /// ```
+    /// # let db = rocksbin::DB::open("db_dir_compare_and_swap").unwrap();
+    /// let locks = db.prefix::<String, u64>(b"locks").unwrap();
+    ///
+    /// assert!(locks.compare_and_swap("printer", None, Some(&1)).unwrap());
+    /// assert!(!locks.compare_and_swap("printer", None, Some(&2)).unwrap());
+    /// assert!(locks.compare_and_swap("printer", Some(&1), None).unwrap());
+    ///
+    /// # drop(locks);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_compare_and_swap").unwrap();
+    /// ```
+    pub fn compare_and_swap<Q>(&self, key: &Q, expected: Option<&V>, new: Option<&V>) -> Result<bool>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+        V: PartialEq,
+    {
+        let key_buf = self.key_buf(key)?;
+        let value_buf = match new {
+            Some(value) => Some(self.value_buf(value)?),
+            None => None,
+        };
+
+        let _guard = self.update_lock.lock().unwrap();
+        if self.get_buf(&key_buf)?.as_ref() != expected {
+            return Ok(false);
+        }
+
+        match value_buf {
+            Some(value_buf) => self.db.put(&key_buf, &value_buf)?,
+            None => self.db.delete(&key_buf)?,
+        }
+        Ok(true)
+    }
+
+    /// Remove a key-value pair, but only if its current value is `expected`.
+    ///
+    /// Returns `true` if the pair was removed. This is the same as
+    /// `compare_and_swap(key, Some(expected), None)`, so it is atomic in the same way.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::get` and `Prefix::remove`
+    pub fn remove_if<Q>(&self, key: &Q, expected: &V) -> Result<bool>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+        V: PartialEq,
+    {
+        self.compare_and_swap(key, Some(expected), None)
+    }
+
+    /// Insert a key-value pair and return the previous value, like `HashMap::insert`.
+    ///
+    /// This is the same as `Prefix::swap`.
+    pub fn insert_get<Q>(&self, key: &Q, value: &V) -> Result<Option<V>>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        self.swap(key, value)
+    }
+
+    /// Remove a key and return its previous value, like `HashMap::remove`.
+    ///
+    /// Like `Prefix::swap` this is atomic with respect to other `swap`, `update_retry` and
+    /// `remove_get` calls on this database.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::get` and `Prefix::remove`
+    pub fn remove_get<Q>(&self, key: &Q) -> Result<Option<V>>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let key_buf = self.key_buf(key)?;
+
+        let _guard = self.update_lock.lock().unwrap();
+        let old_value = self.get_buf(&key_buf)?;
+
+        if old_value.is_some() {
+            self.db.delete(&key_buf)?;
+        }
+        Ok(old_value)
+    }
+
+    /// An iterator visiting all key-value pairs of this prefix.
+    /// The iterator type is `Result<(K, V), Error>`
+    pub fn iter(&self) -> Iter<K, V, C> {
+        self.iter_in(Source::DB(self.db.clone()))
+    }
+
+    /// An iterator visiting all key-value pairs of this prefix that knows how many pairs are
+    /// left, e.g., to show progress or collect into a `Vec` of the right size.
+    /// The iterator type is `Result<(K, V), Error>`
+    ///
+    /// The pairs are counted up front, which visits every key like `Prefix::len`. The iterator
+    /// reads a snapshot taken before counting, so the count stays exact when the prefix is
+    /// modified concurrently, but those modifications are not visited.
+    ///
+    /// # Examples
+    /// ```
+    /// # let db = rocksbin::DB::open("db_dir_sized_iter").unwrap();
+    /// let heights = db.prefix::<String, u64>(b"heights").unwrap();
+    ///
+    /// heights.insert("John", &175).unwrap();
+    /// heights.insert("Lisa", &165).unwrap();
+    ///
+    /// let mut iter = heights.sized_iter();
+    /// assert_eq!(iter.len(), 2);
+    ///
+    /// heights.insert("Bob", &185).unwrap();
+    /// iter.next();
+    /// assert_eq!(iter.len(), 1);
+    ///
+    /// # drop(iter);
+    /// # drop(heights);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_sized_iter").unwrap();
+    /// ```
+    pub fn sized_iter(&self) -> SizedIter<K, V, C> {
+        let snapshot = Arc::new(snapshot::Inner::new(self.db.clone()));
+
+        let mut db_iter = snapshot.raw_iterator();
+        db_iter.seek(&self.prefix);
+        let mut len = 0;
+        while prefixed_entry(&db_iter, &self.prefix).is_some() {
+            len += 1;
+            db_iter.next();
+        }
+
+        SizedIter {
+            iter: self.iter_in(Source::Snapshot(snapshot)),
+            len,
+        }
+    }
+
+    /// Returns the key-value pair with the smallest key of this prefix.
+    ///
+    /// Keys are compared by their serialized bytes, so this is only the smallest key of `K` if
+    /// the prefix is `Prefix::ordered`.
+    ///
+    /// This function will return `Err` if deserializing the key or value fails.
+    pub fn first(&self) -> Result<Option<(K, V)>> {
+        self.iter().next().transpose()
+    }
+
+    /// Returns the key-value pair with the largest key of this prefix.
+    ///
+    /// See `Prefix::first`
+    pub fn last(&self) -> Result<Option<(K, V)>> {
+        self.iter().next_back().transpose()
+    }
+
+    /// Returns the first key-value pair, in the order of `Prefix::iter`, whose value matches `f`.
+    ///
+    /// The scan stops at the first match. Only the keys of matching pairs are deserialized.
+    ///
+    /// This function will return `Err` if deserializing one of the values, or the key of the
+    /// matching pair, fails.
+    ///
+    /// # Examples
+    /// ```
+    /// # let db = rocksbin::DB::open("db_dir_find").unwrap();
+    /// let heights = db.prefix::<String, u64>(b"heights").unwrap();
+    ///
+    /// heights.insert("John", &175).unwrap();
+    /// heights.insert("Lisa", &165).unwrap();
+    ///
+    /// assert_eq!(heights.find(|&height| height < 170).unwrap(), Some(("Lisa".to_string(), 165)));
+    /// assert_eq!(heights.find(|&height| height > 200).unwrap(), None);
+    ///
+    /// # drop(heights);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_find").unwrap();
+    /// ```
+    pub fn find<F: FnMut(&V) -> bool>(&self, mut f: F) -> Result<Option<(K, V)>> {
+        let mut db_iter = self.db.raw_iterator();
+        db_iter.seek(&self.prefix);
+
+        while let Some((k, v)) = prefixed_entry(&db_iter, &self.prefix) {
+            let value = decode_value::<C, _>(&[&self.prefix[..], k].concat(), v, self.checksum, self.values)?;
+            if f(&value) {
+                return Ok(Some((decode_key(k, self.keys)?, value)));
+            }
+            db_iter.next();
+        }
+
+        Ok(None)
+    }
+
+    /// Returns every key-value pair whose value matches `f`, in the order of `Prefix::iter`.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::find`
+    pub fn find_all<F: FnMut(&V) -> bool>(&self, mut f: F) -> Result<Vec<(K, V)>> {
+        let mut db_iter = self.db.raw_iterator();
+        db_iter.seek(&self.prefix);
+
+        let mut found = Vec::new();
+        while let Some((k, v)) = prefixed_entry(&db_iter, &self.prefix) {
+            let value = decode_value::<C, _>(&[&self.prefix[..], k].concat(), v, self.checksum, self.values)?;
+            if f(&value) {
+                found.push((decode_key(k, self.keys)?, value));
+            }
+            db_iter.next();
+        }
+
+        Ok(found)
+    }
+
+    /// An iterator visiting all key-value pairs of this prefix in `source`.
+    fn iter_in(&self, source: Source) -> Iter<K, V, C> {
+        Iter {
+            raw: RawIter::new(source, self.prefix.clone(), &self.prefix, None),
+            checksum: self.checksum,
+            values: self.values,
+            keys: self.keys,
+            _k: PhantomData,
+            _v: PhantomData,
+            _c: PhantomData,
+        }
+    }
+
+    /// Split this prefix into at most `n` contiguous ranges with about the same number of
+    /// key-value pairs, e.g., to process each range with `Prefix::range` on its own thread.
+    ///
+    /// The ranges do not overlap and together cover every key of the prefix, including keys
+    /// inserted after splitting. The rocksdb bindings can not estimate the size of a key range, so
+    /// this visits every key twice, but neither keys nor values are deserialized except for the
+    /// keys at the range boundaries. Fewer than `n` ranges are returned if the prefix has fewer
+    /// than `n` pairs, but always at least one.
+    ///
+    /// This function will return `Err` if one of the following occures:
+    /// - Deserializing one of the boundary keys fails
+    /// - The underlying rocksdb command fails
+    ///
+    /// # Examples
+    /// ```
+    /// # let db = rocksbin::DB::open("db_dir_split_ranges").unwrap();
+    /// let squares = db.prefix::<u64, u64>(b"squares").unwrap();
+    ///
+    /// for n in 0..100 {
+    ///     squares.insert(&n, &(n * n)).unwrap();
+    /// }
+    ///
+    /// let ranges = squares.split_ranges(4).unwrap();
+    /// assert_eq!(ranges.len(), 4);
+    ///
+    /// for range in ranges {
+    ///     assert_eq!(squares.range(range).unwrap().count(), 25);
+    /// }
+    ///
+    /// # drop(squares);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_split_ranges").unwrap();
+    /// ```
+    pub fn split_ranges(&self, n: usize) -> Result<Vec<(Bound<K>, Bound<K>)>> {
+        let len = self.len()?;
+        let n = n.min(len).max(1);
+
+        let mut db_iter = self.db.raw_iterator();
+        db_iter.seek(&self.prefix);
+
+        let mut ranges = Vec::with_capacity(n);
+        let mut start = Bound::Unbounded;
+        let mut index = 0;
+
+        while let Some((k, _)) = prefixed_entry(&db_iter, &self.prefix) {
+            if ranges.len() + 1 < n && index == (ranges.len() + 1) * len / n {
+                let end = Bound::Excluded(decode_key(k, self.keys)?);
+                ranges.push((start, end));
+                start = Bound::Included(decode_key(k, self.keys)?);
+            }
+            index += 1;
+            db_iter.next();
+        }
+
+        ranges.push((start, Bound::Unbounded));
+        Ok(ranges)
+    }
+
+    /// An iterator visiting the key-value pairs of this prefix with keys in `range`.
+    /// The iterator type is `Result<(K, V), Error>`
+    ///
+    /// The bounds are compared with the serialized keys, so the result only matches the logical
+    /// order of `K` if the prefix is `Prefix::ordered` and `K` is ordered by it.
+    ///
+    /// This function will return `Err` if serializing one of the bounds fails.
+    ///
+    /// # Examples
+    /// ```
+    /// # let db = rocksbin::DB::open("db_dir_range").unwrap();
+    /// let squares = db.prefix::<u64, u64>(b"squares").unwrap().ordered();
+    ///
+    /// for n in 0..100 {
+    ///     squares.insert(&n, &(n * n)).unwrap();
+    /// }
+    ///
+    /// let entries = squares.range(10..=12).unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+    /// assert_eq!(entries, vec![(10, 100), (11, 121), (12, 144)]);
+    ///
+    /// assert_eq!(squares.range(10..20).unwrap().count(), 10);
+    /// assert_eq!(squares.range(..5).unwrap().count(), 5);
+    /// assert_eq!(squares.range(95..).unwrap().count(), 5);
+    ///
+    /// # drop(squares);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_range").unwrap();
+    /// ```
+    pub fn range<Q, R>(&self, range: R) -> Result<Iter<K, V, C>>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let (start, end) = self.range_bufs(range)?;
+
+        Ok(Iter {
+            raw: RawIter::new(Source::DB(self.db.clone()), self.prefix.clone(), &start, end),
+            checksum: self.checksum,
+            values: self.values,
+            keys: self.keys,
+            _k: PhantomData,
+            _v: PhantomData,
+            _c: PhantomData,
+        })
+    }
+
+    /// Count the key-value pairs of this prefix whose keys are in `range`.
+    ///
+    /// Keys are ordered like in `Prefix::range`. Neither keys nor values are deserialized, so
+    /// this is faster than counting the pairs of `Prefix::range`.
+    ///
+    /// This function will return `Err` if serializing one of the bounds fails.
+    ///
+    /// # Examples
+    /// ```
+    /// # let db = rocksbin::DB::open("db_dir_count_range").unwrap();
+    /// let squares = db.prefix::<u64, u64>(b"squares").unwrap().ordered();
+    ///
+    /// for n in 0..100 {
+    ///     squares.insert(&n, &(n * n)).unwrap();
+    /// }
+    ///
+    /// assert_eq!(squares.count_range(10..20).unwrap(), 10);
+    /// assert_eq!(squares.count_range(95..).unwrap(), 5);
+    ///
+    /// # drop(squares);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_count_range").unwrap();
+    /// ```
+    pub fn count_range<Q, R>(&self, range: R) -> Result<usize>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let (start, end) = self.range_bufs(range)?;
+        let mut raw = RawIter::new(Source::DB(self.db.clone()), self.prefix.clone(), &start, end);
+
+        let mut count = 0;
+        while raw.front().is_some() {
+            count += 1;
+            raw.next();
+        }
+        Ok(count)
+    }
+
+    /// The full key `range` starts at and the full key it stops at, see `Prefix::range`.
+    fn range_bufs<Q, R>(&self, range: R) -> Result<(Vec<u8>, Option<Vec<u8>>)>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let start = match range.start_bound() {
+            Bound::Included(start) => self.key_buf(start)?,
+            Bound::Excluded(start) => {
+                // The smallest key greater than `start`
+                let mut start = self.key_buf(start)?;
+                start.push(0);
+                start
+            }
+            Bound::Unbounded => self.prefix.clone(),
+        };
+
+        let end = match range.end_bound() {
+            Bound::Included(end) => {
+                let mut end = self.key_buf(end)?;
+                end.push(0);
+                Some(end)
+            }
+            Bound::Excluded(end) => Some(self.key_buf(end)?),
+            Bound::Unbounded => None,
+        };
+
+        Ok((start, end))
+    }
+
+    /// An iterator visiting the key-value pairs of this prefix whose keys start with
+    /// `key_prefix`.
+    /// The iterator type is `Result<(K, V), Error>`
+    ///
+    /// This is meant for composite keys like `(user_id, timestamp)`, where `key_prefix` is the
+    /// leading part of the key, e.g., a `user_id`. It only works if the serialized `key_prefix`
+    /// is a byte prefix of the serialized keys it should match, which holds for leading tuple
+    /// and struct fields, but not for the leading part of a string or `Vec`, which are
+    /// serialized with their length first.
+    ///
+    /// This function will return `Err` if serializing `key_prefix` fails.
+    ///
+    /// # Examples
+    /// ```
+    /// # let db = rocksbin::DB::open("db_dir_scan_prefix").unwrap();
+    /// let logins = db.prefix::<(u32, u64), String>(b"logins").unwrap();
+    ///
+    /// logins.insert(&(1, 1000), &"Oslo".to_string()).unwrap();
+    /// logins.insert(&(2, 1010), &"Bergen".to_string()).unwrap();
+    /// logins.insert(&(1, 1020), &"Oslo".to_string()).unwrap();
+    ///
+    /// assert_eq!(logins.scan_prefix(&1u32).unwrap().count(), 2);
+    ///
+    /// # drop(logins);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_scan_prefix").unwrap();
+    /// ```
+    pub fn scan_prefix<P: Serialize + ?Sized>(&self, key_prefix: &P) -> Result<Iter<K, V, C>> {
+        let mut start = self.prefix.clone();
+        encode_key(&mut start, key_prefix, self.keys)?;
+        let end = successor(&start);
+
+        Ok(Iter {
+            raw: RawIter::new(Source::DB(self.db.clone()), self.prefix.clone(), &start, end),
+            checksum: self.checksum,
+            values: self.values,
+            keys: self.keys,
+            _k: PhantomData,
+            _v: PhantomData,
+            _c: PhantomData,
+        })
+    }
+
+    /// An iterator visiting the key-value pairs of this prefix, starting at the first key that is
+    /// equal to or ordered after `key`.
+    /// The iterator type is `Result<(K, V), Error>`
+    ///
+    /// This is useful for resuming an iteration. Keys are ordered like in `Prefix::range`.
+    ///
+    /// This function will return `Err` if serializing the key fails.
+    pub fn iter_from<Q>(&self, key: &Q) -> Result<Iter<K, V, C>>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        self.range((Bound::Included(key), Bound::Unbounded))
+    }
+
+    /// Send every key-value pair of this prefix over `tx` from a background thread.
+    ///
+    /// The thread stops when all pairs are sent or when the receiving end of the channel is
+    /// dropped. Errors are sent over the channel like in `Prefix::iter`.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::sync::mpsc;
+    ///
+    /// # let db = rocksbin::DB::open("db_dir_stream_to").unwrap();
+    /// let heights = db.prefix::<String, u64>(b"heights").unwrap();
+    ///
+    /// heights.insert("John", &175).unwrap();
+    /// heights.insert("Lisa", &165).unwrap();
+    ///
+    /// let (tx, rx) = mpsc::channel();
+    /// heights.stream_to(tx);
+    ///
+    /// assert_eq!(rx.iter().count(), 2);
+    ///
+    /// # drop(heights);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_stream_to").unwrap();
+    /// ```
+    pub fn stream_to(&self, tx: mpsc::Sender<Result<(K, V)>>) -> thread::JoinHandle<()>
+    where
+        K: Send + 'static,
+        V: Send + 'static,
+    {
+        let db = self.db.clone();
+        let prefix = self.prefix.clone();
+        let checksum = self.checksum;
+        let values = self.values;
+        let keys = self.keys;
+
+        thread::spawn(move || {
+            let iter = Iter {
+                raw: RawIter::new(Source::DB(db), prefix.clone(), &prefix, None),
+                checksum,
+                values,
+                keys,
+                _k: PhantomData,
+                _v: PhantomData,
+                _c: PhantomData::<C>,
+            };
+
+            for entry in iter {
+                if tx.send(entry).is_err() {
+                    break;
+                }
+            }
+        })
+    }
+
+    /// An iterator visiting all key-value pairs of this prefix in chunks of `chunk_size`.
+    /// The iterator type is `Result<Vec<(K, V)>, Error>`
+    ///
+    /// Every chunk except the last one contains exactly `chunk_size` pairs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is 0.
+    pub fn iter_chunks(&self, chunk_size: usize) -> Chunks<K, V, C> {
+        assert!(chunk_size != 0, "chunk size must be non-zero");
+
+        Chunks {
+            iter: self.iter(),
+            chunk_size,
+        }
+    }
+
+    /// An iterator visiting every `n`th key-value pair of this prefix, starting with the first.
+    /// The iterator type is `Result<(K, V), Error>`
+    ///
+    /// The skipped pairs are not deserialized.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is 0.
+    pub fn iter_step(&self, n: usize) -> IterStep<K, V, C> {
+        assert!(n != 0, "step must be non-zero");
+
+        IterStep {
+            raw: RawIter::new(Source::DB(self.db.clone()), self.prefix.clone(), &self.prefix, None),
+            checksum: self.checksum,
+            values: self.values,
+            keys: self.keys,
+            step: n,
+            _k: PhantomData,
+            _v: PhantomData,
+            _c: PhantomData,
+        }
+    }
+
+    /// An iterator visiting all keys of this prefix.
+    /// The iterator type is `Result<K, Error>`
+    pub fn keys(&self) -> Keys<K> {
+        self.keys_in(Source::DB(self.db.clone()))
+    }
+
+    /// An iterator visiting all keys of this prefix in `source`.
+    fn keys_in(&self, source: Source) -> Keys<K> {
+        Keys {
+            raw: RawIter::new(source, self.prefix.clone(), &self.prefix, None),
+            keys: self.keys,
+            _k: PhantomData,
+        }
+    }
+
+    /// An iterator visiting all values of this prefix.
+    /// The iterator type is `Result<V, Error>`
+    pub fn values(&self) -> Values<V, C> {
+        self.values_in(Source::DB(self.db.clone()))
+    }
+
+    /// An iterator visiting all values of this prefix in `source`.
+    fn values_in(&self, source: Source) -> Values<V, C> {
+        Values {
+            raw: RawIter::new(source, self.prefix.clone(), &self.prefix, None),
+            checksum: self.checksum,
+            values: self.values,
+            _v: PhantomData,
+            _c: PhantomData,
+        }
+    }
+
+    /// Collect all key-value pairs of this prefix into a `Vec`, stopping at the first error.
+    ///
+    /// Counting the pairs first would mean iterating twice, so no capacity is reserved up front.
+    ///
+    /// This function will return `Err` if deserializing one of the keys or values fails.
+    pub fn to_vec(&self) -> Result<Vec<(K, V)>> {
+        self.iter().collect()
+    }
+
+    /// Collect all keys of this prefix into a `Vec`, stopping at the first error.
+    ///
+    /// This function will return `Err` if deserializing one of the keys fails.
+    pub fn keys_vec(&self) -> Result<Vec<K>> {
+        self.keys().collect()
+    }
+
+    /// Collect all values of this prefix into a `Vec`, stopping at the first error.
+    ///
+    /// This function will return `Err` if deserializing one of the values fails.
+    pub fn values_vec(&self) -> Result<Vec<V>> {
+        self.values().collect()
+    }
+
+    /// An iterator visiting all keys of this prefix together with the serialized values.
+    /// The iterator type is `Result<(K, Vec<u8>), Error>`
+    ///
+    /// This is useful for moving values around without deserializing them.
+    pub fn iter_raw_values(&self) -> IterRawValues<K> {
+        IterRawValues {
+            raw: RawIter::new(Source::DB(self.db.clone()), self.prefix.clone(), &self.prefix, None),
+            keys: self.keys,
+            _k: PhantomData,
+        }
+    }
+
+    /// Merge every entry of `other` into this prefix.
+    ///
+    /// For every key in `other`, `combine` is given the existing value in this prefix, if any,
+    /// and the value from `other`, and the result is inserted into this prefix. Returns the
+    /// number of entries merged.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::get` and `Prefix::insert`.
+    /// Entries merged before the error occured stay merged.
+    ///
+    /// # Examples
+    /// ```
+    /// # let db = rocksbin::DB::open("db_dir_merge_from").unwrap();
+    /// let counts = db.prefix::<String, u64>(b"counts").unwrap();
+    /// let new_counts = db.prefix::<String, u64>(b"new_counts").unwrap();
+    ///
+    /// counts.insert("salmon", &10).unwrap();
+    /// new_counts.insert("salmon", &5).unwrap();
+    /// new_counts.insert("cod", &3).unwrap();
+    ///
+    /// counts.merge_from(&new_counts, |old, new| old.unwrap_or(0) + new).unwrap();
+    ///
+    /// assert_eq!(counts.get("salmon").unwrap(), Some(15));
+    /// assert_eq!(counts.get("cod").unwrap(), Some(3));
+    ///
+    /// # drop(counts);
+    /// # drop(new_counts);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_merge_from").unwrap();
+    /// ```
+    pub fn merge_from<F: Fn(Option<V>, V) -> V>(&self, other: &Prefix<K, V, C>, combine: F) -> Result<u64> {
+        let mut count = 0;
+
+        for entry in other.iter() {
+            let (key, value) = entry?;
+            let merged = combine(self.get(&key)?, value);
+            self.insert(&key, &merged)?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    /// Export the entries of this prefix into a new database at `target`.
+    ///
+    /// The entries are read from a snapshot, so the export is consistent even if the prefix is
+    /// written to concurrently. Keys are stored under the empty prefix `b""` in the new database,
+    /// so the data can be read by opening it with the same `BincodeConfig` and creating the prefix
+    /// `b""`. The configuration is stored in the new database when it is not the default. The
+    /// entries are written in batches of 1000.
+    ///
+    /// This function will return `Err` if one of the following occures:
+    /// - `target` already exists, nothing is written to it then
+    /// - The underlying rocksdb commands fail, the partially written database at `target` is
+    ///   removed then
+    ///
+    /// # Examples
+    /// ```
+    /// # let db = rocksbin::DB::open("db_dir_export").unwrap();
+    /// let heights = db.prefix::<String, u64>(b"heights").unwrap();
+    ///
+    /// heights.insert("John", &175).unwrap();
+    /// heights.export_checkpoint("db_dir_export_heights").unwrap();
+    ///
+    /// let export = rocksbin::DB::open("db_dir_export_heights").unwrap();
+    /// let exported_heights = export.prefix::<String, u64>(b"").unwrap();
+    ///
+    /// assert_eq!(exported_heights.get("John").unwrap(), Some(175));
+    ///
+    /// # drop(exported_heights);
+    /// # drop(export);
+    /// # drop(heights);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_export").unwrap();
+    /// # std::fs::remove_dir_all("db_dir_export_heights").unwrap();
+    /// ```
+    pub fn export_checkpoint<P: AsRef<Path>>(&self, target: P) -> Result<()> {
+        // rocksdb 0.11 has no error_if_exists, so check for an existing database by hand
+        let target = target.as_ref();
+        if target.exists() {
+            return Err(Box::new(ErrorKind::Io(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", target.display()),
+            ))));
+        }
+
+        let result = self.write_checkpoint(target);
+        if result.is_err() {
+            // The export is already failing, a leftover directory is the lesser problem
+            let _ = fs::remove_dir_all(target);
+        }
+        result
+    }
+
+    fn write_checkpoint(&self, target: &Path) -> Result<()> {
+        let mut opts = rocksdb::Options::default();
+        opts.create_if_missing(true);
+        let target = rocksdb::DB::open(&opts, target)?;
+
+        let snapshot = self.db.snapshot();
+        let mut batch = rocksdb::WriteBatch::default();
+
+        // Copy the stored configuration, it is only there when it is not the default
+        if let Some(config) = snapshot.get(CONFIG_KEY)? {
+            batch.put(CONFIG_KEY, &config)?;
+        }
+
+        let export_prefix = serialize_prefix(b"");
+        let mut db_iter = snapshot.raw_iterator();
+        db_iter.seek(&self.prefix);
+
+        while let Some((k, v)) = prefixed_entry(&db_iter, &self.prefix) {
+            batch.put(&[&export_prefix[..], k].concat(), v)?;
+            if batch.len() >= BULK_BATCH_SIZE {
+                target.write(mem::take(&mut batch))?;
+            }
+            db_iter.next();
+        }
+
+        target.write(batch)?;
+        Ok(())
+    }
+
+    /// Write the entries of this prefix to `w` as a JSON array of `{"key": ..., "value": ...}`
+    /// objects.
+    ///
+    /// This is meant for inspecting the contents of a prefix, and requires the `json` feature.
+    /// The entries are written one at a time, and can be read back with `Prefix::import_json`.
+    ///
+    /// This function will return `Err` if one of the following occures:
+    /// - Deserializing one of the keys or values fails
+    /// - Serializing one of the keys or values as JSON, or writing to `w`, fails
+    #[cfg(feature = "json")]
+    pub fn dump_json<W: std::io::Write>(&self, w: W) -> Result<()> {
+        serde_json::to_writer(w, &JsonDump { prefix: self })?;
+        Ok(())
+    }
+
+    /// Insert the entries of a JSON array written by `Prefix::dump_json` into this prefix, in one
+    /// atomic batch.
+    ///
+    /// The entries are read one at a time, only their serialized form is kept until the batch is
+    /// written. Existing entries with other keys are kept. Requires the `json` feature.
+    ///
+    /// This function will return `Err` if one of the following occures:
+    /// - Reading from `r` or parsing the JSON fails, in which case nothing is inserted
+    /// - Serializing one of the keys or values fails, in which case nothing is inserted
+    /// - The underlying rocksdb command fails
+    ///
+    /// # Examples
+    /// ```
+    /// # let db = rocksbin::DB::open("db_dir_import_json").unwrap();
+    /// let heights = db.prefix::<String, u64>(b"heights").unwrap();
+    ///
+    /// heights.import_json(r#"[{"key": "John", "value": 175}]"#.as_bytes()).unwrap();
+    ///
+    /// assert_eq!(heights.get("John").unwrap(), Some(175));
+    ///
+    /// # drop(heights);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_import_json").unwrap();
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn import_json<R: std::io::Read>(&self, r: R) -> Result<()> {
+        let mut batch = rocksdb::WriteBatch::default();
+
+        let mut deserializer = serde_json::Deserializer::from_reader(r);
+        deserializer.deserialize_seq(JsonImport {
+            prefix: self,
+            batch: &mut batch,
+        })?;
+        deserializer.end()?;
+
+        self.db.write(batch)?;
+        Ok(())
+    }
+
+    /// Read every entry of this prefix so its blocks are loaded into the rocksdb block cache.
+    ///
+    /// Following reads of the prefix are then more likely to be served from memory. Nothing is
+    /// deserialized. How much of the prefix stays cached depends on the size of the block cache.
+    ///
+    /// This function will return `Err` if the underlying rocksdb command fails.
+    pub fn prewarm(&self) -> Result<()> {
+        let mut db_iter = self.db.raw_iterator();
+        db_iter.seek(&self.prefix);
+
+        while prefixed_entry(&db_iter, &self.prefix).is_some() {
+            db_iter.next();
+        }
+
+        Ok(())
+    }
+
+    /// Compact the key range of this prefix, e.g., after `Prefix::clear` to reclaim the space of
+    /// the removed entries and drop the tombstones that slow down iteration until rocksdb
+    /// compacts them on its own.
+    ///
+    /// Keys of other prefixes are not affected, but rocksdb may compact the SST files they share
+    /// with this prefix as well.
+    ///
+    /// # Examples
+    /// ```
+    /// # let db = rocksbin::DB::open("db_dir_compact").unwrap();
+    /// let logs = db.prefix::<u64, String>(b"logs").unwrap();
+    ///
+    /// for n in 0..1000 {
+    ///     logs.insert(&n, &format!("entry {}", n)).unwrap();
+    /// }
+    ///
+    /// logs.clear().unwrap();
+    /// logs.compact().unwrap();
+    ///
+    /// assert!(logs.is_empty().unwrap());
+    ///
+    /// # drop(logs);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_compact").unwrap();
+    /// ```
+    pub fn compact(&self) -> Result<()> {
+        let end = successor(&self.prefix);
+        self.db.compact_range(Some(&self.prefix), end.as_ref().map(|end| &end[..]));
+        Ok(())
+    }
+
+    /// Compact this prefix in `chunks` steps instead of all at once.
+    ///
+    /// The prefix is split into `chunks` key ranges holding about the same number of entries,
+    /// which are compacted one after another. This spreads out the I/O of compacting a large
+    /// prefix. Finding the ranges requires a scan over the keys of the prefix.
+    ///
+    /// This function will return `Err` if the underlying rocksdb command fails.
+    pub fn compact_incremental(&self, chunks: usize) -> Result<()> {
+        let mut db_iter = self.db.raw_iterator();
+        db_iter.seek(&self.prefix);
+
+        let mut len = 0;
+        while prefixed_entry(&db_iter, &self.prefix).is_some() {
+            len += 1;
+            db_iter.next();
+        }
+
+        let chunk_len = (len / chunks.max(1)).max(1);
+        let mut bounds = vec![self.prefix.clone()];
+
+        db_iter.seek(&self.prefix);
+        let mut i = 0;
+        while let Some((k, _)) = prefixed_entry(&db_iter, &self.prefix) {
+            if i > 0 && i % chunk_len == 0 && bounds.len() < chunks {
+                bounds.push([&self.prefix[..], k].concat());
+            }
+            i += 1;
+            db_iter.next();
+        }
+
+        let end = successor(&self.prefix);
+        for (j, start) in bounds.iter().enumerate() {
+            let stop = bounds.get(j + 1).or(end.as_ref());
+            self.db.compact_range(Some(start), stop.map(|stop| &stop[..]));
+        }
+
+        Ok(())
+    }
+
+    /// Count the entries of this prefix in buckets given by `f`, in a single pass.
+    ///
+    /// Only the count of each bucket is kept, so memory use grows with the number of distinct
+    /// buckets, not with the number of entries.
+    ///
+    /// This function will return `Err` if deserializing one of the entries fails.
+    ///
+    /// # Examples
+    /// ```
+    /// # let db = rocksbin::DB::open("db_dir_count_by").unwrap();
+    /// let cities = db.prefix::<String, String>(b"cities").unwrap();
+    ///
+    /// cities.insert("John", &"Oslo".to_string()).unwrap();
+    /// cities.insert("Lisa", &"Bergen".to_string()).unwrap();
+    /// cities.insert("Kari", &"Oslo".to_string()).unwrap();
+    ///
+    /// let counts = cities.count_by(|_, city| city.clone()).unwrap();
+    /// assert_eq!(counts["Oslo"], 2);
+    /// assert_eq!(counts["Bergen"], 1);
+    ///
+    /// # drop(cities);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_count_by").unwrap();
+    /// ```
+    pub fn count_by<B, F>(&self, f: F) -> Result<HashMap<B, u64>>
+    where
+        B: Eq + Hash,
+        F: Fn(&K, &V) -> B,
+    {
+        let mut counts = HashMap::new();
+        for entry in self.iter() {
+            let (key, value) = entry?;
+            *counts.entry(f(&key, &value)).or_insert(0) += 1;
+        }
+        Ok(counts)
+    }
+
+    /// Count the number of distinct results of `f` applied to every value of this prefix.
+    ///
+    /// The count is exact: every distinct result is kept in a `HashSet` until the scan is done,
+    /// so memory use grows with the number of distinct results, not with the number of entries.
+    ///
+    /// This function will return `Err` if deserializing one of the values fails.
+    ///
+    /// # Examples
+    /// ```
+    /// # let db = rocksbin::DB::open("db_dir_count_distinct").unwrap();
+    /// let cities = db.prefix::<String, String>(b"cities").unwrap();
+    ///
+    /// cities.insert("John", &"Oslo".to_string()).unwrap();
+    /// cities.insert("Lisa", &"Bergen".to_string()).unwrap();
+    /// cities.insert("Kari", &"Oslo".to_string()).unwrap();
+    ///
+    /// assert_eq!(cities.count_distinct_by(|city| city.clone()).unwrap(), 2);
+    ///
+    /// # drop(cities);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_count_distinct").unwrap();
+    /// ```
+    pub fn count_distinct_by<T, F>(&self, f: F) -> Result<usize>
+    where
+        T: Eq + Hash,
+        F: Fn(&V) -> T,
+    {
+        let mut seen = HashSet::new();
+        for value in self.values() {
+            seen.insert(f(&value?));
+        }
+        Ok(seen.len())
+    }
+
+    /// Count the number of distinct leading components of the keys of this prefix.
+    ///
+    /// This is meant for composite keys like `(group_id, item_id)`, where `P` is the type of the
+    /// first component. Instead of visiting every key, the scan seeks past all keys of a group
+    /// once the group has been counted, so the cost depends on the number of groups rather than
+    /// on the number of entries.
+    ///
+    /// This function will return `Err` if deserializing the leading component of a key fails.
+    ///
+    /// # Examples
+    /// ```
+    /// # let db = rocksbin::DB::open("db_dir_count_groups").unwrap();
+    /// let items = db.prefix::<(u64, String), u64>(b"items").unwrap();
+    ///
+    /// items.insert(&(1, "salmon".to_string()), &10).unwrap();
+    /// items.insert(&(1, "cod".to_string()), &20).unwrap();
+    /// items.insert(&(2, "salmon".to_string()), &30).unwrap();
+    ///
+    /// assert_eq!(items.count_distinct_key_prefix::<u64>().unwrap(), 2);
+    ///
+    /// # drop(items);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_count_groups").unwrap();
+    /// ```
+    pub fn count_distinct_key_prefix<P: Serialize + DeserializeOwned>(&self) -> Result<usize> {
+        let mut db_iter = self.db.raw_iterator();
+        db_iter.seek(&self.prefix);
+
+        let mut count = 0;
+
+        while let Some((k, _)) = prefixed_entry(&db_iter, &self.prefix) {
+            let group: P = decode_key(k, self.keys)?;
+            let group_len = self.keys.serialized_size(&group)? as usize;
+            let next_group = successor(&[&self.prefix[..], &k[..group_len]].concat());
+
+            count += 1;
+            match next_group {
+                Some(next_group) => db_iter.seek(&next_group),
+                None => break,
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Compute the changes made to this prefix between two snapshots.
+    ///
+    /// Both snapshots are walked in key order. Keys only present in `new` are reported as
+    /// `Change::Insert`, keys only present in `old` as `Change::Remove` and keys whose value
+    /// differs as `Change::Update`.
+    ///
+    /// This function will return `Err` if deserializing one of the changed keys or values fails.
+    pub fn changed_between(&self, old: &Snapshot, new: &Snapshot) -> Result<Vec<Change<K, V>>> {
+        let mut old_iter = old.raw_iterator();
+        let mut new_iter = new.raw_iterator();
+        old_iter.seek(&self.prefix);
+        new_iter.seek(&self.prefix);
+
+        let mut changes = Vec::new();
+        let value = |k: &[u8], v: &[u8]| decode_value::<C, _>(&[&self.prefix[..], k].concat(), v, self.checksum, self.values);
+
+        loop {
+            let (change, next_old, next_new) = match (
+                prefixed_entry(&old_iter, &self.prefix),
+                prefixed_entry(&new_iter, &self.prefix),
+            ) {
+                (None, None) => break,
+                (Some((k, _)), None) => (Some(Change::Remove(decode_key(k, self.keys)?)), true, false),
+                (None, Some((k, v))) => (
+                    Some(Change::Insert(decode_key(k, self.keys)?, value(k, v)?)),
+                    false,
+                    true,
+                ),
+                (Some((old_k, old_v)), Some((new_k, new_v))) => match old_k.cmp(new_k) {
+                    Ordering::Less => (Some(Change::Remove(decode_key(old_k, self.keys)?)), true, false),
+                    Ordering::Greater => (
+                        Some(Change::Insert(decode_key(new_k, self.keys)?, value(new_k, new_v)?)),
+                        false,
+                        true,
+                    ),
+                    Ordering::Equal if old_v != new_v => (
+                        Some(Change::Update(decode_key(new_k, self.keys)?, value(new_k, new_v)?)),
+                        true,
+                        true,
+                    ),
+                    Ordering::Equal => (None, true, true),
+                },
+            };
+
+            changes.extend(change);
+            if next_old {
+                old_iter.next();
+            }
+            if next_new {
+                new_iter.next();
+            }
+        }
+
+        Ok(changes)
+    }
+
+    /// Collect the key-value pairs of this prefix until their serialized values would take up
+    /// more than `max_bytes`.
+    ///
+    /// Returns the collected pairs and whether there are more pairs left in the prefix. The size
+    /// of a pair is the length of its serialized value, so a value larger than `max_bytes` is
+    /// never collected.
+    ///
+    /// This function will return `Err` if deserializing one of the collected keys or values fails.
+    pub fn collect_until_bytes(&self, max_bytes: usize) -> Result<(Vec<(K, V)>, bool)> {
+        let mut db_iter = self.db.raw_iterator();
+        db_iter.seek(&self.prefix);
+
+        let mut entries = Vec::new();
+        let mut total = 0;
+
+        while let Some((k, v)) = prefixed_entry(&db_iter, &self.prefix) {
+            total += v.len();
+            if total > max_bytes {
+                return Ok((entries, true));
+            }
+            let value = decode_value::<C, _>(&[&self.prefix[..], k].concat(), v, self.checksum, self.values)?;
+            entries.push((decode_key(k, self.keys)?, value));
+            db_iter.next();
+        }
+
+        Ok((entries, false))
+    }
+
+    /// Apply a list of operations atomically.
+    ///
+    /// The operations are applied in order, either all of them are written or none of them are.
+    ///
+    /// This function will return `Err` if one of the following occures:
+    /// - Serializing one of the keys or values fails, in which case nothing is written
+    /// - The underlying rocksdb command fails
+    ///
+    /// # Examples
+    /// ```
+    /// use rocksbin::Op;
+    ///
+    /// # let db = rocksbin::DB::open("db_dir_apply").unwrap();
+    /// let heights = db.prefix::<String, u64>(b"heights").unwrap();
+    ///
+    /// heights.insert("John", &175).unwrap();
+    ///
+    /// heights.apply(&[
+    ///     Op::Remove("John".to_string()),
+    ///     Op::Insert("Lisa".to_string(), 165),
+    /// ]).unwrap();
+    ///
+    /// assert_eq!(heights.get("John").unwrap(), None);
+    /// assert_eq!(heights.get("Lisa").unwrap(), Some(165));
+    ///
+    /// # drop(heights);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_apply").unwrap();
+    /// ```
+    pub fn apply(&self, ops: &[Op<K, V>]) -> Result<()> {
+        let mut batch = rocksdb::WriteBatch::default();
+
+        for op in ops {
+            match *op {
+                Op::Insert(ref key, ref value) => batch.put(&self.key_buf(key)?, &self.value_buf(value)?)?,
+                Op::Remove(ref key) => batch.delete(&self.key_buf(key)?)?,
+            }
+        }
+
+        self.db.write(batch)?;
+        Ok(())
+    }
+
+    /// Move every value of this prefix to the key computed by `f`.
+    ///
+    /// All old keys are removed before any of the new keys are written, in one atomic batch, so
+    /// a new key is never overwritten by the removal of an old key that happens to be equal. If
+    /// `f` maps several keys to the same new key, the value of the last of them in iteration
+    /// order is kept. Values are moved without being deserialized, but all of them are buffered
+    /// in memory until the batch is written.
+    ///
+    /// Returns the number of entries that were moved.
+    ///
+    /// This function will return `Err` if one of the following occures:
+    /// - Deserializing one of the old keys or serializing one of the new keys fails, in which
+    ///   case nothing is written
+    /// - The underlying rocksdb command fails
+    pub fn rekey<F: Fn(&K) -> K>(&self, f: F) -> Result<u64> {
+        let mut db_iter = self.db.raw_iterator();
+        db_iter.seek(&self.prefix);
+
+        let mut batch = rocksdb::WriteBatch::default();
+        let mut moved = Vec::new();
+
+        while let Some((k, v)) = prefixed_entry(&db_iter, &self.prefix) {
+            let new_key = self.key_buf(&f(&decode_key(k, self.keys)?))?;
+            batch.delete(&[&self.prefix[..], k].concat())?;
+            moved.push((new_key, v.to_vec()));
+            db_iter.next();
+        }
+
+        for (key, value) in &moved {
+            batch.put(key, value)?;
+        }
+
+        self.db.write(batch)?;
+        Ok(moved.len() as u64)
+    }
+
+    /// Copy every entry of this prefix into `truthy` if `f` returns `true` for its key, and into
+    /// `falsy` otherwise. If `clear_source` is set the entries are removed from this prefix as
+    /// they are copied, moving them instead.
+    ///
+    /// Values are copied without being deserialized when both prefixes store them the same way,
+    /// otherwise they are serialized again as the destination expects, see `Prefix::checksummed`
+    /// and `Prefix::canonical`. The entries are written in batches of 1000. Returns the number of
+    /// entries copied into `truthy` and `falsy`.
+    ///
+    /// This function will return `Err` if one of the following occures:
+    /// - `truthy` or `falsy` is a prefix of another database, nothing is written then
+    /// - Deserializing one of the keys, or one of the values that has to be serialized again,
+    ///   fails
+    /// - The underlying rocksdb command fails
+    ///
+    /// Batches written before the error occured are not undone.
+    pub fn partition_by<F: Fn(&K) -> bool>(
+        &self,
+        truthy: &Prefix<K, V, C>,
+        falsy: &Prefix<K, V, C>,
+        clear_source: bool,
+        f: F,
+    ) -> Result<(u64, u64)> {
+        if !Arc::ptr_eq(&self.db, &truthy.db) || !Arc::ptr_eq(&self.db, &falsy.db) {
+            return Err(Box::new(ErrorKind::ForeignPrefix));
+        }
+
+        let mut db_iter = self.db.raw_iterator();
+        db_iter.seek(&self.prefix);
+
+        let mut batch = rocksdb::WriteBatch::default();
+        let mut counts = (0, 0);
+
+        while let Some((k, v)) = prefixed_entry(&db_iter, &self.prefix) {
+            let key: K = decode_key(k, self.keys)?;
+            let dest = if f(&key) {
+                counts.0 += 1;
+                truthy
+            } else {
+                counts.1 += 1;
+                falsy
+            };
+
+            let key_buf = [&self.prefix[..], k].concat();
+            let same_layout =
+                dest.checksum == self.checksum && dest.canonical == self.canonical && dest.values == self.values;
+            let value = if same_layout {
+                dest.limits.check_value(strip_checksum(&key_buf, v, self.checksum)?.len())?;
+                v.to_vec()
+            } else {
+                dest.value_buf(&decode_value::<C, V>(&key_buf, v, self.checksum, self.values)?)?
+            };
+
+            // Removed first, so an entry whose destination is this prefix is kept
+            if clear_source {
+                batch.delete(&key_buf)?;
+            }
+            batch.put(&dest.key_buf(&key)?, &value)?;
+            if batch.len() >= BULK_BATCH_SIZE {
+                self.db.write(mem::take(&mut batch))?;
+            }
+            db_iter.next();
+        }
+
+        self.db.write(batch)?;
+        Ok(counts)
+    }
+}
+
+impl<K: Serialize + DeserializeOwned, V: Serialize + DeserializeOwned> Prefix<K, V, Bincode> {
+    /// A cursor visiting all key-value pairs of this prefix, deserializing them without copying.
+    ///
+    /// See `IterRef`
+    ///
+    /// # Examples
+    /// ```
+    /// # let db = rocksbin::DB::open("db_dir_iter_ref").unwrap();
+    /// let cities = db.prefix::<String, String>(b"cities").unwrap();
+    ///
+    /// cities.insert("John", &"Oslo".to_string()).unwrap();
+    /// cities.insert("Lisa", &"Bergen".to_string()).unwrap();
+    ///
+    /// let mut iter = cities.iter_ref();
+    /// let mut total = 0;
+    /// while let Some(entry) = iter.next_ref::<&str, &str>() {
+    ///     let (name, city) = entry.unwrap();
+    ///     total += name.len() + city.len();
+    /// }
+    /// assert_eq!(total, 18);
+    ///
+    /// # drop(cities);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_iter_ref").unwrap();
+    /// ```
+    pub fn iter_ref(&self) -> IterRef<K, V> {
+        IterRef {
+            raw: RawIter::new(Source::DB(self.db.clone()), self.prefix.clone(), &self.prefix, None),
+            checksum: self.checksum,
+            values: self.values,
+            keys: self.keys,
+            started: false,
+            _k: PhantomData,
+            _v: PhantomData,
+        }
+    }
+}
+
+/// A write operation on a prefix, see `Prefix::apply`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Op<K, V> {
+    /// Insert a key-value pair.
+    Insert(K, V),
+    /// Remove a key.
+    Remove(K),
+}
+
+/// Serializes the entries of a prefix for `Prefix::dump_json`.
+#[cfg(feature = "json")]
+struct JsonDump<'a, K: 'a, V: 'a, C: 'a> {
+    prefix: &'a Prefix<K, V, C>,
+}
+
+#[cfg(feature = "json")]
+impl<'a, K, V, C> Serialize for JsonDump<'a, K, V, C>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+    C: Codec,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::Error;
+
+        let mut seq = serializer.serialize_seq(None)?;
+
+        for entry in self.prefix.iter() {
+            let (key, value) = entry.map_err(S::Error::custom)?;
+            seq.serialize_element(&JsonEntry { key, value })?;
+        }
+
+        seq.end()
+    }
+}
+
+/// A single `{"key": ..., "value": ...}` object of a `JsonDump`.
+#[cfg(feature = "json")]
+struct JsonEntry<K, V> {
+    key: K,
+    value: V,
+}
+
+#[cfg(feature = "json")]
+impl<K: Serialize, V: Serialize> Serialize for JsonEntry<K, V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut entry = serializer.serialize_struct("Entry", 2)?;
+        entry.serialize_field("key", &self.key)?;
+        entry.serialize_field("value", &self.value)?;
+        entry.end()
+    }
+}
+
+#[cfg(feature = "json")]
+impl<'de, K: Deserialize<'de>, V: Deserialize<'de>> Deserialize<'de> for JsonEntry<K, V> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        deserializer.deserialize_struct("Entry", &["key", "value"], JsonEntryVisitor { _e: PhantomData })
+    }
+}
+
+#[cfg(feature = "json")]
+struct JsonEntryVisitor<K, V> {
+    _e: PhantomData<JsonEntry<K, V>>,
+}
+
+#[cfg(feature = "json")]
+impl<'de, K: Deserialize<'de>, V: Deserialize<'de>> Visitor<'de> for JsonEntryVisitor<K, V> {
+    type Value = JsonEntry<K, V>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "an object with a key and a value")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<Self::Value, A::Error> {
+        use serde::de::{Error, IgnoredAny};
+
+        let mut key = None;
+        let mut value = None;
+
+        while let Some(field) = map.next_key::<String>()? {
+            match &field[..] {
+                "key" => key = Some(map.next_value()?),
+                "value" => value = Some(map.next_value()?),
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        Ok(JsonEntry {
+            key: key.ok_or_else(|| A::Error::missing_field("key"))?,
+            value: value.ok_or_else(|| A::Error::missing_field("value"))?,
+        })
+    }
+}
+
+/// Reads the entries of a `JsonDump` into a batch for `Prefix::import_json`.
+#[cfg(feature = "json")]
+struct JsonImport<'a, K: 'a, V: 'a, C: 'a> {
+    prefix: &'a Prefix<K, V, C>,
+    batch: &'a mut rocksdb::WriteBatch,
+}
+
+#[cfg(feature = "json")]
+impl<'a, 'de, K, V, C> Visitor<'de> for JsonImport<'a, K, V, C>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+    C: Codec,
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a seq of prefix entries")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<(), A::Error> {
+        use serde::de::Error;
+
+        while let Some(entry) = seq.next_element::<JsonEntry<K, V>>()? {
+            let key_buf = self.prefix.key_buf(&entry.key).map_err(A::Error::custom)?;
+            let value_buf = self.prefix.value_buf(&entry.value).map_err(A::Error::custom)?;
+            self.batch.put(&key_buf, &value_buf).map_err(A::Error::custom)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A value of one of two types, see `Prefix::get_either`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Either<L, R> {
+    Left(L),
+    Right(R),
+}
+
+/// A change to a single key, see `Prefix::changed_between`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change<K, V> {
+    /// The key was inserted with this value.
+    Insert(K, V),
+    /// The value of the key was changed to this value.
+    Update(K, V),
+    /// The key was removed.
+    Remove(K),
+}
+
+/// Serialize `key` into `buf` with `config`, the configuration of the keys of a prefix, see
+/// `Prefix::ordered`.
+fn encode_key<Q: Serialize + ?Sized>(buf: &mut Vec<u8>, key: &Q, config: BincodeConfig) -> Result<()> {
+    config.serialize_into(buf, key)?;
+    Ok(())
+}
+
+/// Deserialize a key serialized by `encode_key`.
+fn decode_key<'a, K: Deserialize<'a>>(data: &'a [u8], config: BincodeConfig) -> Result<K> {
+    Ok(config.deserialize_key(data)?)
+}
+
+/// Deserialize the value stored under `key`, verifying and stripping its checksum if `checksum`
+/// is set.
+fn decode_value<C: Codec, V: DeserializeOwned>(key: &[u8], data: &[u8], checksum: bool, config: BincodeConfig) -> Result<V> {
+    C::deserialize_with(strip_checksum(key, data, checksum)?, config)
+}
+
+/// The serialized value in `data`, stored under `key`, with its checksum verified and stripped
+/// if `checksum` is set.
+fn strip_checksum<'a>(key: &[u8], data: &'a [u8], checksum: bool) -> Result<&'a [u8]> {
+    if !checksum {
+        return Ok(data);
+    }
+
+    if data.len() < 4 {
+        return Err(Box::new(ErrorKind::Corruption { key: key.to_vec() }));
+    }
+    let (value, stored) = data.split_at(data.len() - 4);
+    if stored != &crc32(value).to_le_bytes()[..] {
+        return Err(Box::new(ErrorKind::Corruption { key: key.to_vec() }));
+    }
+    Ok(value)
+}
+
+/// The CRC-32 (IEEE) checksum of `data`.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+/// Formats bytes like a byte string literal, e.g., `b"\x05\0\0\0users"`.
+struct ByteStr<'a>(&'a [u8]);
+
+impl<'a> fmt::Debug for ByteStr<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("b\"")?;
+        for &byte in self.0 {
+            if byte == 0 {
+                f.write_str("\\0")?;
+            } else {
+                for c in ascii::escape_default(byte) {
+                    fmt::Write::write_char(f, c as char)?;
+                }
+            }
+        }
+        f.write_str("\"")
+    }
+}
+
+/// The smallest byte string that is greater than every byte string starting with `key`, or
+/// `None` if there is no such string.
+fn successor(key: &[u8]) -> Option<Vec<u8>> {
+    let end = key.iter().rposition(|&b| b != 0xff)?;
+    let mut next = key[..=end].to_vec();
+    next[end] += 1;
+    Some(next)
+}
+
+/// The key, with the prefix stripped, and the value `db_iter` points at. Returns `None` if the
+/// iterator is invalid or has moved past `prefix`.
+fn prefixed_entry<'a>(db_iter: &'a rocksdb::DBRawIterator, prefix: &[u8]) -> Option<(&'a [u8], &'a [u8])> {
+    // The slices borrow the iterator so it can not be moved while they are alive
+    let key = unsafe { db_iter.key_inner() }?;
+    let value = unsafe { db_iter.value_inner() }?;
+
+    if key.starts_with(prefix) {
+        Some((&key[prefix.len()..], value))
+    } else {
+        None
+    }
+}
+
+/// What a `RawIter` reads from.
+enum Source {
+    DB(Arc<rocksdb::DB>),
+    Snapshot(Arc<snapshot::Inner>),
+    Cf(Arc<rocksdb::DB>, rocksdb::ColumnFamily),
+}
+
+impl Source {
+    fn raw_iterator(&self) -> rocksdb::DBRawIterator {
+        match *self {
+            Source::DB(ref db) => db.raw_iterator(),
+            Source::Snapshot(ref snapshot) => snapshot.raw_iterator(),
+            // Creating a column family iterator never fails
+            Source::Cf(ref db, cf) => db.raw_iterator_cf(cf).unwrap(),
+        }
+    }
+}
+
+/// A raw iterator over the entries of a prefix that can be consumed from both ends.
+///
+/// The back iterator is only created once it is first used. Each end points at the next entry
+/// it yields, and an end stops once it would pass the entry the other end points at.
+///
+/// Keys are sorted, so once an end reaches a key outside the prefix or range every key after it
+/// is outside as well. The end is then never moved again, so it keeps returning `None`.
+struct RawIter {
+    front: rocksdb::DBRawIterator,
+    back: Option<rocksdb::DBRawIterator>,
+    // Declared after the iterators so it is dropped after them
+    source: Source,
+    prefix: Vec<u8>,
+    /// The full key the iteration stops at, see `Prefix::range`.
+    end: Option<Vec<u8>>,
+}
+
+// rocksdb iterators can be used from any thread as long as they are not used from several at once,
+// and `source` keeps the database or snapshot they read from alive
+unsafe impl Send for RawIter {}
+
+impl RawIter {
+    fn new(source: Source, prefix: Vec<u8>, start: &[u8], end: Option<Vec<u8>>) -> RawIter {
+        let mut front = source.raw_iterator();
+        front.seek(start);
+
+        RawIter {
+            front,
+            back: None,
+            source,
+            prefix,
+            end,
+        }
+    }
+
+    /// Whether `key` is part of the iteration.
+    fn in_range(&self, key: &[u8]) -> bool {
+        match self.end {
+            Some(ref end) => key.starts_with(&self.prefix) && key < &end[..],
+            None => key.starts_with(&self.prefix),
+        }
+    }
+
+    /// The full key and the value the front points at.
+    fn front(&self) -> Option<(&[u8], &[u8])> {
+        // The slices borrow the iterator so it can not be moved while they are alive
+        let key = unsafe { self.front.key_inner() }?;
+        let value = unsafe { self.front.value_inner() }?;
+
+        if !self.in_range(key) {
+            return None;
+        }
+        if let Some(ref back) = self.back {
+            match unsafe { back.key_inner() } {
+                Some(back_key) if key <= back_key => (),
+                _ => return None,
+            }
+        }
+        Some((key, value))
+    }
+
+    fn next(&mut self) {
+        self.front.next();
+    }
+
+    /// Create the back iterator if it does not exist yet.
+    fn init_back(&mut self) {
+        if self.back.is_none() {
+            let mut back = self.source.raw_iterator();
+            match self.end.clone().or_else(|| successor(&self.prefix)) {
+                Some(end) => {
+                    back.seek_for_prev(&end);
+                    if back.key().as_ref() == Some(&end) {
+                        back.prev();
+                    }
+                }
+                None => back.seek_to_last(),
+            }
+            self.back = Some(back);
+        }
+    }
+
+    /// The full key and the value the back points at, `init_back` must be called first.
+    fn back(&self) -> Option<(&[u8], &[u8])> {
+        let back = self.back.as_ref()?;
+        let key = unsafe { back.key_inner() }?;
+        let value = unsafe { back.value_inner() }?;
+
+        if !key.starts_with(&self.prefix) {
+            return None;
+        }
+        match unsafe { self.front.key_inner() } {
+            Some(front_key) if self.in_range(front_key) && front_key <= key => Some((key, value)),
+            _ => None,
+        }
+    }
+
+    fn next_back(&mut self) {
+        if let Some(ref mut back) = self.back {
+            back.prev();
+        }
+    }
+}
+
+/// An iterator over the key-value pairs of a prefix.
+pub struct Iter<K, V, C = Bincode> {
+    raw: RawIter,
+    checksum: bool,
+    values: BincodeConfig,
+    keys: BincodeConfig,
+    _k: PhantomData<K>,
+    _v: PhantomData<V>,
+    _c: PhantomData<C>,
+}
+
+impl<K: DeserializeOwned, V: DeserializeOwned, C: Codec> Iter<K, V, C> {
+    fn decode(&self, key: &[u8], value: &[u8]) -> Result<(K, V)> {
+        Ok((
+            decode_key(&key[self.raw.prefix.len()..], self.keys)?,
+            decode_value::<C, _>(key, value, self.checksum, self.values)?,
+        ))
+    }
+}
+
+impl<K: DeserializeOwned, V: DeserializeOwned, C: Codec> Iterator for Iter<K, V, C> {
+    type Item = Result<(K, V)>; // :(
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.raw.front().map(|(k, v)| self.decode(k, v));
+        if item.is_some() {
+            self.raw.next();
+        }
+        item
+    }
+}
+
+impl<K: DeserializeOwned, V: DeserializeOwned, C: Codec> DoubleEndedIterator for Iter<K, V, C> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.raw.init_back();
+        let item = self.raw.back().map(|(k, v)| self.decode(k, v));
+        if item.is_some() {
+            self.raw.next_back();
+        }
+        item
+    }
+}
+
+impl<K: DeserializeOwned, V: DeserializeOwned, C: Codec> FusedIterator for Iter<K, V, C> {}
+
+/// An iterator over the key-value pairs of a prefix that knows how many pairs are left.
+///
+/// See `Prefix::sized_iter`
+pub struct SizedIter<K, V, C = Bincode> {
+    iter: Iter<K, V, C>,
+    len: usize,
+}
+
+impl<K: DeserializeOwned, V: DeserializeOwned, C: Codec> Iterator for SizedIter<K, V, C> {
+    type Item = Result<(K, V)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.iter.next();
+        if item.is_some() {
+            self.len -= 1;
+        }
+        item
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<K: DeserializeOwned, V: DeserializeOwned, C: Codec> DoubleEndedIterator for SizedIter<K, V, C> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let item = self.iter.next_back();
+        if item.is_some() {
+            self.len -= 1;
+        }
+        item
+    }
+}
+
+impl<K: DeserializeOwned, V: DeserializeOwned, C: Codec> ExactSizeIterator for SizedIter<K, V, C> {}
+
+impl<K: DeserializeOwned, V: DeserializeOwned, C: Codec> FusedIterator for SizedIter<K, V, C> {}
+
+/// An iterator over every `n`th key-value pair of a prefix.
+pub struct IterStep<K, V, C = Bincode> {
+    raw: RawIter,
+    checksum: bool,
+    values: BincodeConfig,
+    keys: BincodeConfig,
+    step: usize,
+    _k: PhantomData<K>,
+    _v: PhantomData<V>,
+    _c: PhantomData<C>,
+}
+
+impl<K: DeserializeOwned, V: DeserializeOwned, C: Codec> Iterator for IterStep<K, V, C> {
+    type Item = Result<(K, V)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.raw.front().map(|(k, v)| {
+            Ok((
+                decode_key(&k[self.raw.prefix.len()..], self.keys)?,
+                decode_value::<C, _>(k, v, self.checksum, self.values)?,
+            ))
+        });
+
+        if item.is_some() {
+            for _ in 0..self.step {
+                if self.raw.front().is_none() {
+                    break;
+                }
+                self.raw.next();
+            }
+        }
+        item
+    }
+}
+
+impl<K: DeserializeOwned, V: DeserializeOwned, C: Codec> FusedIterator for IterStep<K, V, C> {}
+
+/// An iterator over the key-value pairs of a prefix in chunks.
+pub struct Chunks<K, V, C = Bincode> {
+    iter: Iter<K, V, C>,
+    chunk_size: usize,
+}
+
+impl<K: DeserializeOwned, V: DeserializeOwned, C: Codec> Iterator for Chunks<K, V, C> {
+    type Item = Result<Vec<(K, V)>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut chunk = Vec::with_capacity(self.chunk_size);
+
+        while chunk.len() < self.chunk_size {
+            match self.iter.next() {
+                Some(Ok(entry)) => chunk.push(entry),
+                Some(Err(e)) => return Some(Err(e)),
+                None => break,
+            }
+        }
+
+        if chunk.is_empty() {
+            None
+        } else {
+            Some(Ok(chunk))
+        }
+    }
+}
+
+/// A cursor over the key-value pairs of a prefix that deserializes them without copying.
+///
+/// This is not an `Iterator`, as the deserialized pairs may borrow from the cursor: keys and
+/// values of `Prefix<String, String>` can for example be read as `&str`, which saves the
+/// allocations `Prefix::iter` makes for every pair. The borrowed pairs are only valid until
+/// `IterRef::next_ref` is called again.
+///
+/// Created with `Prefix::iter_ref`.
+pub struct IterRef<K, V> {
+    raw: RawIter,
+    checksum: bool,
+    values: BincodeConfig,
+    keys: BincodeConfig,
+    started: bool,
+    _k: PhantomData<K>,
+    _v: PhantomData<V>,
+}
+
+impl<K, V> IterRef<K, V> {
+    /// Move to the next key-value pair and deserialize it as `(KB, VB)`, which can borrow from
+    /// the cursor.
+    ///
+    /// Returns `None` once all pairs are visited.
+    pub fn next_ref<'a, KB, VB>(&'a mut self) -> Option<Result<(KB, VB)>>
+    where
+        KB: Deserialize<'a>,
+        VB: Deserialize<'a>,
+    {
+        if self.started && self.raw.front.valid() {
+            self.raw.next();
+        }
+        self.started = true;
+
+        let (key, value) = self.raw.front()?;
+        let decode = || {
+            let value = strip_checksum(key, value, self.checksum)?;
+            Ok((
+                decode_key(&key[self.raw.prefix.len()..], self.keys)?,
+                self.values.deserialize_value(value)?,
+            ))
+        };
+        Some(decode())
+    }
+}
+
+/// An iterator over the keys of a prefix.
+pub struct Keys<K> {
+    raw: RawIter,
+    keys: BincodeConfig,
+    _k: PhantomData<K>,
+}
+
+impl<K: DeserializeOwned> Iterator for Keys<K> {
+    type Item = Result<K>; // :(
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self
+            .raw
+            .front()
+            .map(|(k, _)| decode_key(&k[self.raw.prefix.len()..], self.keys));
+        if item.is_some() {
+            self.raw.next();
+        }
+        item
+    }
+}
+
+impl<K: DeserializeOwned> DoubleEndedIterator for Keys<K> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.raw.init_back();
+        let item = self
+            .raw
+            .back()
+            .map(|(k, _)| decode_key(&k[self.raw.prefix.len()..], self.keys));
+        if item.is_some() {
+            self.raw.next_back();
+        }
+        item
+    }
+}
+
+impl<K: DeserializeOwned> FusedIterator for Keys<K> {}
+
+/// An iterator over the values of a prefix.
+pub struct Values<V, C = Bincode> {
+    raw: RawIter,
+    checksum: bool,
+    values: BincodeConfig,
+    _v: PhantomData<V>,
+    _c: PhantomData<C>,
+}
+
+impl<V: DeserializeOwned, C: Codec> Iterator for Values<V, C> {
+    type Item = Result<V>; // :(
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.raw.front().map(|(k, v)| decode_value::<C, _>(k, v, self.checksum, self.values));
+        if item.is_some() {
+            self.raw.next();
+        }
+        item
+    }
+}
+
+impl<V: DeserializeOwned, C: Codec> DoubleEndedIterator for Values<V, C> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.raw.init_back();
+        let item = self.raw.back().map(|(k, v)| decode_value::<C, _>(k, v, self.checksum, self.values));
+        if item.is_some() {
+            self.raw.next_back();
+        }
+        item
+    }
+}
+
+impl<V: DeserializeOwned, C: Codec> FusedIterator for Values<V, C> {}
+
+/// An iterator over the keys and serialized values of a prefix.
+pub struct IterRawValues<K> {
+    raw: RawIter,
+    keys: BincodeConfig,
+    _k: PhantomData<K>,
+}
+
+impl<K: DeserializeOwned> Iterator for IterRawValues<K> {
+    type Item = Result<(K, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self
+            .raw
+            .front()
+            .map(|(k, v)| Ok((decode_key(&k[self.raw.prefix.len()..], self.keys)?, v.to_vec())));
+        if item.is_some() {
+            self.raw.next();
+        }
+        item
+    }
+}
+
+impl<K: DeserializeOwned> FusedIterator for IterRawValues<K> {}
@@ -0,0 +1,322 @@
+use bincode;
+use rocksdb::{self, MergeOperands};
+use serde::{de::DeserializeOwned, Serialize};
+
+use std::any::type_name;
+use std::sync::{Arc, RwLock, Weak};
+
+use {BincodeConfig, ErrorKind, Result};
+
+type MergeFn = Box<dyn Fn(Option<&[u8]>, &mut MergeOperands) -> Option<Vec<u8>> + Send + Sync>;
+type PartialMergeFn = Box<dyn Fn(&mut MergeOperands) -> Option<Vec<u8>> + Send + Sync>;
+
+/// A merge operator as rocksdb takes it.
+type MergeFnPtr = fn(&[u8], Option<&[u8]>, &mut MergeOperands) -> Option<Vec<u8>>;
+
+/// Builds the merge functions of a prefix once the bincode configuration of the database is
+/// known.
+type BuildFn = Box<dyn FnOnce(BincodeConfig) -> MergeFns + Send>;
+
+/// The merge functions of a prefix.
+pub(crate) struct MergeFns {
+    full: MergeFn,
+    /// Combines operands without an existing value, the full merge is used if there is none.
+    partial: Option<PartialMergeFn>,
+    /// The name of the value type, see `DB::merge_prefix`.
+    value_type: &'static str,
+}
+
+/// The merge functions of the prefixes of a database, by serialized prefix.
+pub(crate) type Merges = Vec<(Vec<u8>, MergeFns)>;
+
+/// The merge functions of the prefixes of a database, given when it is opened with
+/// `DB::open_with_merge_operators`.
+///
+/// rocksdb merges operands in the background, e.g., during compaction, so the merge functions
+/// have to be known as soon as the database is opened. A database that contains merge operands
+/// must therefore always be opened with the merge operators of their prefixes. Otherwise rocksdb
+/// fails the merge, and reports the database as corrupted.
+///
+/// Merge prefixes store plain values, so they can not be checksummed or canonical, see
+/// `Prefix::merge`.
+///
+/// # Examples
+/// ```
+/// use rocksbin::{BincodeConfig, MergeOperators, DB};
+///
+/// # fn main() {
+/// let merges = MergeOperators::new()
+///     .add::<u64, _>(b"max_heights", |existing, operands| {
+///         operands.into_iter().chain(existing).max().unwrap_or(0)
+///     })
+///     .counter(b"visits");
+///
+/// let mut opts = rocksdb::Options::default();
+/// opts.create_if_missing(true);
+///
+/// let db = DB::open_with_merge_operators("db_dir_merge_operators", opts, BincodeConfig::default(), merges).unwrap();
+/// let max_heights = db.merge_prefix::<String, u64>(b"max_heights").unwrap();
+///
+/// max_heights.merge("John", &175).unwrap();
+/// max_heights.merge("John", &170).unwrap();
+///
+/// assert_eq!(max_heights.get("John").unwrap(), Some(175));
+///
+/// # drop(max_heights);
+/// # drop(db);
+/// # std::fs::remove_dir_all("db_dir_merge_operators").unwrap();
+/// # }
+/// ```
+#[derive(Default)]
+pub struct MergeOperators {
+    merges: Vec<(Vec<u8>, BuildFn)>,
+}
+
+impl MergeOperators {
+    /// An empty set of merge operators.
+    pub fn new() -> MergeOperators {
+        MergeOperators::default()
+    }
+
+    /// Register `merge` as the merge function of `prefix`, whose values are of type `V`.
+    ///
+    /// `merge` is given the existing value, if any, and the operands merged into the key since,
+    /// in the order they were merged, and returns the new value. rocksdb may also call it with
+    /// `None` and only some of the operands to combine them ahead of time, so the result of
+    /// merging the operands one by one or in groups must be the same. Use
+    /// `MergeOperators::add_with_partial` to combine them with another function.
+    pub fn add<V, F>(self, prefix: &[u8], merge: F) -> MergeOperators
+    where
+        V: Serialize + DeserializeOwned + 'static,
+        F: Fn(Option<V>, Vec<V>) -> V + Send + Sync + 'static,
+    {
+        self.add_fns(prefix, merge, None::<fn(Vec<V>) -> Option<V>>)
+    }
+
+    /// Register merge functions like `MergeOperators::add`, with a separate function for
+    /// combining operands ahead of time.
+    ///
+    /// `full_merge` is given the existing value, if any, and the operands, like the merge
+    /// function of `MergeOperators::add`. `partial_merge` is given only some of the operands,
+    /// without the existing value, and combines them into one operand. It returns `None` if the
+    /// operands can not be combined, rocksdb then keeps them as they are until the full merge.
+    pub fn add_with_partial<V, F, P>(self, prefix: &[u8], full_merge: F, partial_merge: P) -> MergeOperators
+    where
+        V: Serialize + DeserializeOwned + 'static,
+        F: Fn(Option<V>, Vec<V>) -> V + Send + Sync + 'static,
+        P: Fn(Vec<V>) -> Option<V> + Send + Sync + 'static,
+    {
+        self.add_fns(prefix, full_merge, Some(partial_merge))
+    }
+
+    /// Register `prefix` as a prefix of `i64` counters, see `DB::counter_prefix`.
+    pub fn counter(self, prefix: &[u8]) -> MergeOperators {
+        self.add_with_partial(
+            prefix,
+            |existing, operands| existing.unwrap_or(0) + operands.into_iter().sum::<i64>(),
+            |operands| Some(operands.into_iter().sum()),
+        )
+    }
+
+    fn add_fns<V, F, P>(mut self, prefix: &[u8], full_merge: F, partial_merge: Option<P>) -> MergeOperators
+    where
+        V: Serialize + DeserializeOwned + 'static,
+        F: Fn(Option<V>, Vec<V>) -> V + Send + Sync + 'static,
+        P: Fn(Vec<V>) -> Option<V> + Send + Sync + 'static,
+    {
+        let build = move |config: BincodeConfig| {
+            let full: MergeFn = Box::new(move |existing, operands| {
+                let existing = match existing {
+                    Some(data) => Some(config.deserialize_value(data).ok()?),
+                    None => None,
+                };
+                let operands = operands
+                    .map(|data| config.deserialize_value(data))
+                    .collect::<bincode::Result<Vec<V>>>()
+                    .ok()?;
+
+                config.serialize(&full_merge(existing, operands)).ok()
+            });
+            let partial = partial_merge.map(|partial_merge| -> PartialMergeFn {
+                Box::new(move |operands| {
+                    let operands = operands
+                        .map(|data| config.deserialize_value(data))
+                        .collect::<bincode::Result<Vec<V>>>()
+                        .ok()?;
+
+                    config.serialize(&partial_merge(operands)?).ok()
+                })
+            });
+
+            MergeFns {
+                full,
+                partial,
+                value_type: type_name::<V>(),
+            }
+        };
+
+        let prefix = ::serialize_prefix(prefix);
+        self.merges.retain(|(p, _)| *p != prefix);
+        self.merges.push((prefix, Box::new(build)));
+        self
+    }
+
+    pub(crate) fn build(self, config: BincodeConfig) -> Merges {
+        self.merges
+            .into_iter()
+            .map(|(prefix, build)| (prefix, build(config)))
+            .collect()
+    }
+}
+
+/// The name of the merge operator installed on databases with merge operators.
+const NAME: &str = "rocksbin_merge";
+
+/// How many databases with merge operators can be open at once.
+const SLOTS: usize = 32;
+
+/// A slot of `SLOTS`, used by one open database.
+enum Slot {
+    Free,
+    /// Claimed by a database that is being opened.
+    Opening(Arc<Merges>),
+    Open(Weak<rocksdb::DB>, Arc<Merges>),
+}
+
+impl Slot {
+    fn merges(&self) -> Option<&Arc<Merges>> {
+        match *self {
+            Slot::Free => None,
+            Slot::Opening(ref merges) | Slot::Open(_, ref merges) => Some(merges),
+        }
+    }
+
+    fn is_free(&self) -> bool {
+        match *self {
+            Slot::Free => true,
+            Slot::Opening(_) => false,
+            Slot::Open(ref db, _) => db.upgrade().is_none(),
+        }
+    }
+}
+
+/// The merge functions of every open database with merge operators.
+///
+/// rocksdb only accepts a plain function pointer as merge operator, which can not tell which
+/// database it merges for. Every database is therefore given a slot with merge operators of its
+/// own, `merge_fn::<SLOT>`, which run the merge functions of that slot.
+static MERGES: RwLock<Vec<Slot>> = RwLock::new(Vec::new());
+
+macro_rules! slot_fns {
+    ($f:ident, $($slot:expr),*) => {
+        [$($f::<$slot>),*]
+    };
+}
+
+static FULL_MERGES: [MergeFnPtr; SLOTS] = slot_fns!(
+    full_merge, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25,
+    26, 27, 28, 29, 30, 31
+);
+static PARTIAL_MERGES: [MergeFnPtr; SLOTS] = slot_fns!(
+    partial_merge, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24,
+    25, 26, 27, 28, 29, 30, 31
+);
+
+/// A slot claimed for a database that is being opened, freed again if opening fails.
+pub(crate) struct Claim {
+    slot: usize,
+    opened: bool,
+}
+
+impl Claim {
+    /// Claim a free slot for a database with `merges`.
+    ///
+    /// This function will return `Err` if every slot is used by another open database.
+    pub(crate) fn new(merges: Merges) -> Result<Claim> {
+        let mut slots = MERGES.write().unwrap();
+        if slots.is_empty() {
+            slots.extend((0..SLOTS).map(|_| Slot::Free));
+        }
+
+        // Slots of dropped databases are only reused once every slot was used, as rocksdb may
+        // still be merging while a dropped database is closed
+        let slot = slots
+            .iter()
+            .position(|slot| matches!(*slot, Slot::Free))
+            .or_else(|| slots.iter().position(Slot::is_free))
+            .ok_or(ErrorKind::TooManyMergeDatabases)?;
+        slots[slot] = Slot::Opening(Arc::new(merges));
+
+        Ok(Claim { slot, opened: false })
+    }
+
+    /// Install the merge operators of the slot on `opts`.
+    pub(crate) fn set_merge_operator(&self, opts: &mut rocksdb::Options) {
+        opts.set_merge_operator(NAME, FULL_MERGES[self.slot], Some(PARTIAL_MERGES[self.slot]));
+    }
+
+    /// Give the slot to the opened database, it is free again once the database is dropped.
+    pub(crate) fn opened(mut self, db: &Arc<rocksdb::DB>) {
+        let mut slots = MERGES.write().unwrap();
+        let merges = slots[self.slot].merges().unwrap().clone();
+        slots[self.slot] = Slot::Open(Arc::downgrade(db), merges);
+        self.opened = true;
+    }
+}
+
+impl Drop for Claim {
+    fn drop(&mut self) {
+        if !self.opened {
+            MERGES.write().unwrap()[self.slot] = Slot::Free;
+        }
+    }
+}
+
+/// The merge functions of `db`.
+fn merges_of(db: &Arc<rocksdb::DB>) -> Option<Arc<Merges>> {
+    MERGES.read().unwrap().iter().find_map(|slot| match *slot {
+        Slot::Open(ref owner, ref merges) if owner.as_ptr() == Arc::as_ptr(db) => Some(merges.clone()),
+        _ => None,
+    })
+}
+
+/// The name of the value type of the merge functions registered for `prefix` in `db`, if any.
+pub(crate) fn value_type(db: &Arc<rocksdb::DB>, prefix: &[u8]) -> Option<&'static str> {
+    merges_of(db)?
+        .iter()
+        .find(|(p, _)| *p == prefix)
+        .map(|(_, f)| f.value_type)
+}
+
+/// Run `merge` with the merge functions of the longest prefix of `key` in `slot`. Fails the
+/// merge if there are no such functions.
+fn with_fns<T, F: FnOnce(&MergeFns) -> Option<T>>(slot: usize, key: &[u8], merge: F) -> Option<T> {
+    // The functions are not run with the lock held, as they may take a while
+    let merges = MERGES.read().unwrap()[slot].merges()?.clone();
+
+    merges
+        .iter()
+        .filter(|(prefix, _)| key.starts_with(prefix))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .and_then(|(_, f)| merge(f))
+}
+
+/// The merge operator of a slot, it runs the function registered for the longest prefix of
+/// `key`.
+fn full_merge<const SLOT: usize>(key: &[u8], existing: Option<&[u8]>, operands: &mut MergeOperands) -> Option<Vec<u8>> {
+    with_fns(SLOT, key, |f| (f.full)(existing, operands))
+}
+
+/// The partial merge operator of a slot, it runs the partial merge function registered for the
+/// longest prefix of `key`, or the full merge function without an existing value if there is
+/// none. rocksdb keeps the operands as they are if this returns `None`.
+fn partial_merge<const SLOT: usize>(
+    key: &[u8],
+    _existing: Option<&[u8]>,
+    operands: &mut MergeOperands,
+) -> Option<Vec<u8>> {
+    with_fns(SLOT, key, |f| match f.partial {
+        Some(ref partial) => partial(operands),
+        None => (f.full)(None, operands),
+    })
+}
@@ -0,0 +1,138 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+use std::borrow::Borrow;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use {decode_value, Bincode, BincodeConfig, Codec, ErrorKind, Prefix, Result};
+
+/// The id of the next `MultiGet`, so keys can be checked against the values they are read from.
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// A set of keys of any prefixes of a database that are read together.
+///
+/// Every key that is added returns a `MultiGetKey`, which reads its value as the type of its
+/// prefix from the `MultiGetValues` returned by `DB::get_many`. All keys are read from the same
+/// snapshot, so the values are consistent with each other.
+///
+/// See `DB::multi_get`
+///
+/// # Examples
+/// ```
+/// # let db = rocksbin::DB::open("db_dir_multi_get_many").unwrap();
+/// let users = db.prefix::<u64, String>(b"users").unwrap();
+/// let settings = db.prefix::<String, bool>(b"settings").unwrap();
+///
+/// users.insert(&1, &"John".to_string()).unwrap();
+/// settings.insert("dark_mode", &true).unwrap();
+///
+/// let mut reads = db.multi_get();
+/// let user = reads.add(&users, &1).unwrap();
+/// let dark_mode = reads.add(&settings, "dark_mode").unwrap();
+///
+/// let values = db.get_many(reads).unwrap();
+/// assert_eq!(values.get(&user).unwrap(), Some("John".to_string()));
+/// assert_eq!(values.get(&dark_mode).unwrap(), Some(true));
+///
+/// # drop(users);
+/// # drop(settings);
+/// # drop(db);
+/// # std::fs::remove_dir_all("db_dir_multi_get_many").unwrap();
+/// ```
+pub struct MultiGet {
+    id: usize,
+    keys: Vec<Vec<u8>>,
+}
+
+impl MultiGet {
+    pub(crate) fn new() -> MultiGet {
+        MultiGet {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            keys: Vec::new(),
+        }
+    }
+
+    /// Add a key of `prefix` to the keys that are read.
+    ///
+    /// This function will return `Err` if the key could not be serialized.
+    pub fn add<K, V, C, Q>(&mut self, prefix: &Prefix<K, V, C>, key: &Q) -> Result<MultiGetKey<V, C>>
+    where
+        K: Serialize + DeserializeOwned + Borrow<Q>,
+        V: Serialize + DeserializeOwned,
+        C: Codec,
+        Q: Serialize + ?Sized,
+    {
+        self.keys.push(prefix.key_buf(key)?);
+
+        Ok(MultiGetKey {
+            id: self.id,
+            index: self.keys.len() - 1,
+            checksum: prefix.checksum,
+            values: prefix.values,
+            _v: PhantomData,
+            _c: PhantomData,
+        })
+    }
+
+    /// The number of keys that are read.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Whether no keys are read.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub(crate) fn keys(&self) -> &[Vec<u8>] {
+        &self.keys
+    }
+
+    pub(crate) fn into_values(self, values: Vec<Option<Vec<u8>>>) -> MultiGetValues {
+        MultiGetValues {
+            id: self.id,
+            keys: self.keys,
+            values,
+        }
+    }
+}
+
+/// A key added to a `MultiGet`, used to read its value from `MultiGetValues`.
+pub struct MultiGetKey<V, C = Bincode> {
+    id: usize,
+    index: usize,
+    checksum: bool,
+    values: BincodeConfig,
+    _v: PhantomData<V>,
+    _c: PhantomData<C>,
+}
+
+/// The values read by `DB::get_many`.
+pub struct MultiGetValues {
+    id: usize,
+    keys: Vec<Vec<u8>>,
+    values: Vec<Option<Vec<u8>>>,
+}
+
+impl MultiGetValues {
+    /// Returns the value of `key`. If there is no such value, `Ok(None)` is returned.
+    ///
+    /// This function will return `Err` if one of the following occures:
+    /// - `key` was added to another `MultiGet` than the one these values were read for
+    /// - Deserializing the value fails
+    pub fn get<V: DeserializeOwned, C: Codec>(&self, key: &MultiGetKey<V, C>) -> Result<Option<V>> {
+        if key.id != self.id {
+            return Err(Box::new(ErrorKind::ForeignMultiGetKey));
+        }
+
+        match self.values[key.index] {
+            Some(ref data) => Ok(Some(decode_value::<C, _>(
+                &self.keys[key.index],
+                data,
+                key.checksum,
+                key.values,
+            )?)),
+            None => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+use std::borrow::Borrow;
+
+use {IterRawValues, Prefix, Result};
+
+/// A prefix whose values are stored as the given bytes, without serializing them.
+///
+/// This is useful for values that are already encoded, e.g., protobuf messages, which would
+/// otherwise be serialized a second time and get a length in front of them. Keys are serialized
+/// like the keys of any other prefix.
+///
+/// Created with `DB::raw_prefix`.
+///
+/// # Examples
+/// ```
+/// # let db = rocksbin::DB::open("db_dir_raw_prefix").unwrap();
+/// let blobs = db.raw_prefix::<String>(b"blobs").unwrap();
+///
+/// blobs.insert("hello", b"\x0a\x05hello").unwrap();
+///
+/// assert_eq!(blobs.get("hello").unwrap(), Some(b"\x0a\x05hello".to_vec()));
+///
+/// # drop(blobs);
+/// # drop(db);
+/// # std::fs::remove_dir_all("db_dir_raw_prefix").unwrap();
+/// ```
+#[derive(Clone)]
+pub struct RawPrefix<K> {
+    prefix: Prefix<K, Vec<u8>>,
+}
+
+impl<K: Serialize + DeserializeOwned> RawPrefix<K> {
+    pub(crate) fn new(prefix: Prefix<K, Vec<u8>>) -> RawPrefix<K> {
+        RawPrefix { prefix }
+    }
+
+    /// Returns the bytes stored under the key.
+    ///
+    /// This function will return `Err` if the key could not be serialized or the underlying
+    /// rocksdb command fails.
+    pub fn get<Q>(&self, key: &Q) -> Result<Option<Vec<u8>>>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let key_buf = self.prefix.key_buf(key)?;
+        Ok(self.prefix.db.get(&key_buf)?.map(|data| data.to_vec()))
+    }
+
+    /// Whether there are bytes stored under the key.
+    ///
+    /// This function will return `Err` in the same cases as `RawPrefix::get`
+    pub fn contains_key<Q>(&self, key: &Q) -> Result<bool>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        self.prefix.contains_key(key)
+    }
+
+    /// Store `value` under the key as is.
+    ///
+    /// This function will return `Err` if the key could not be serialized, the key or value is
+    /// larger than the limits of the database, see `DB::max_value_size`, or the underlying
+    /// rocksdb command fails.
+    pub fn insert<Q>(&self, key: &Q, value: &[u8]) -> Result<()>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let key_buf = self.prefix.key_buf(key)?;
+        self.prefix.limits.check_value(value.len())?;
+        self.prefix.db.put(&key_buf, value)?;
+        Ok(())
+    }
+
+    /// Remove the bytes stored under the key.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::remove`
+    pub fn remove<Q>(&self, key: &Q) -> Result<()>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        self.prefix.remove(key)
+    }
+
+    /// An iterator visiting all keys of this prefix together with their bytes.
+    /// The iterator type is `Result<(K, Vec<u8>), Error>`
+    ///
+    /// Like the other prefix iterators it keeps the database alive and can be sent to other threads.
+    pub fn iter(&self) -> IterRawValues<K> {
+        self.prefix.iter_raw_values()
+    }
+}
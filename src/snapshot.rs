@@ -0,0 +1,161 @@
+use rocksdb;
+use serde::{de::DeserializeOwned, Serialize};
+
+use std::borrow::Borrow;
+use std::mem;
+use std::sync::Arc;
+
+use {decode_value, Bincode, Codec, Iter, Keys, Prefix, Result, Source, Values, DB};
+
+/// A consistent view of a database at the point of creation.
+///
+/// Writes made after the snapshot was taken are not visible through it. The snapshot, and every
+/// prefix read through it, keeps the database open.
+///
+/// See `DB::snapshot`
+///
+/// # Examples
+/// ```
+/// # let db = rocksbin::DB::open("db_dir_snapshot").unwrap();
+/// let heights = db.prefix::<String, u64>(b"heights").unwrap();
+///
+/// heights.insert("John", &175).unwrap();
+///
+/// let snapshot = db.snapshot();
+/// heights.insert("Lisa", &165).unwrap();
+///
+/// let old_heights = snapshot.prefix::<String, u64>(b"heights").unwrap();
+/// assert_eq!(old_heights.get("John").unwrap(), Some(175));
+/// assert_eq!(old_heights.get("Lisa").unwrap(), None);
+///
+/// # drop(old_heights);
+/// # drop(snapshot);
+/// # drop(heights);
+/// # drop(db);
+/// # std::fs::remove_dir_all("db_dir_snapshot").unwrap();
+/// ```
+#[derive(Clone)]
+pub struct Snapshot {
+    inner: Arc<Inner>,
+    db: DB,
+}
+
+/// A rocksdb snapshot together with the database it was taken from.
+pub(crate) struct Inner {
+    // Declared before `_db` so it is dropped first
+    snapshot: rocksdb::Snapshot<'static>,
+    _db: Arc<rocksdb::DB>,
+}
+
+// rocksdb snapshots are immutable, so they can be read from several threads at once
+unsafe impl Send for Inner {}
+unsafe impl Sync for Inner {}
+
+impl Inner {
+    /// Take a snapshot of `db`.
+    pub(crate) fn new(db: Arc<rocksdb::DB>) -> Inner {
+        // The snapshot borrows the database, which `Inner` keeps alive for as long as the snapshot
+        let snapshot = db.snapshot();
+        let snapshot = unsafe { mem::transmute::<rocksdb::Snapshot<'_>, rocksdb::Snapshot<'static>>(snapshot) };
+
+        Inner { snapshot, _db: db }
+    }
+
+    pub(crate) fn raw_iterator(&self) -> rocksdb::DBRawIterator {
+        self.snapshot.raw_iterator()
+    }
+}
+
+impl Snapshot {
+    pub(crate) fn new(db: DB) -> Snapshot {
+        Snapshot {
+            inner: Arc::new(Inner::new(db.db.clone())),
+            db,
+        }
+    }
+
+    pub(crate) fn raw_iterator(&self) -> rocksdb::DBRawIterator {
+        self.inner.raw_iterator()
+    }
+
+    pub(crate) fn get_raw(&self, key: &[u8]) -> Result<Option<rocksdb::DBVector>> {
+        Ok(self.inner.snapshot.get(key)?)
+    }
+
+    /// Read a prefix as it was when the snapshot was taken.
+    ///
+    /// See `DB::prefix`
+    pub fn prefix<K, V>(&self, prefix: &[u8]) -> Result<SnapshotPrefix<K, V>>
+    where
+        K: Serialize + DeserializeOwned,
+        V: Serialize + DeserializeOwned,
+    {
+        Ok(self.view(self.db.prefix(prefix)?))
+    }
+
+    /// Read `prefix` as it was when the snapshot was taken, keeping its settings like
+    /// `Prefix::ordered` or its codec.
+    pub fn view<K, V, C>(&self, prefix: Prefix<K, V, C>) -> SnapshotPrefix<K, V, C>
+    where
+        K: Serialize + DeserializeOwned,
+        V: Serialize + DeserializeOwned,
+        C: Codec,
+    {
+        SnapshotPrefix {
+            prefix,
+            snapshot: self.inner.clone(),
+        }
+    }
+}
+
+/// A read-only prefix of a `Snapshot`.
+pub struct SnapshotPrefix<K, V, C = Bincode> {
+    prefix: Prefix<K, V, C>,
+    snapshot: Arc<Inner>,
+}
+
+impl<K: Serialize + DeserializeOwned, V: Serialize + DeserializeOwned, C: Codec> SnapshotPrefix<K, V, C> {
+    /// Returns the value coresponing to the key when the snapshot was taken.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::get`
+    pub fn get<Q>(&self, key: &Q) -> Result<Option<V>>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let key_buf = self.prefix.key_buf(key)?;
+        match self.snapshot.snapshot.get(&key_buf)? {
+            Some(data) => Ok(Some(decode_value::<C, _>(&key_buf, &data, self.prefix.checksum, self.prefix.values)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Check if the prefix contained a key when the snapshot was taken.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::get`
+    pub fn contains_key<Q>(&self, key: &Q) -> Result<bool>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        self.get(key).map(|v| v.is_some())
+    }
+
+    /// An iterator visiting all key-value pairs of the prefix when the snapshot was taken.
+    /// The iterator type is `Result<(K, V), Error>`
+    pub fn iter(&self) -> Iter<K, V, C> {
+        self.prefix.iter_in(Source::Snapshot(self.snapshot.clone()))
+    }
+
+    /// An iterator visiting all keys of the prefix when the snapshot was taken.
+    /// The iterator type is `Result<K, Error>`
+    pub fn keys(&self) -> Keys<K> {
+        self.prefix.keys_in(Source::Snapshot(self.snapshot.clone()))
+    }
+
+    /// An iterator visiting all values of the prefix when the snapshot was taken.
+    /// The iterator type is `Result<V, Error>`
+    pub fn values(&self) -> Values<V, C> {
+        self.prefix.values_in(Source::Snapshot(self.snapshot.clone()))
+    }
+}
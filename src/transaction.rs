@@ -0,0 +1,151 @@
+use rocksdb;
+use serde::{de::DeserializeOwned, Serialize};
+
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashSet};
+
+use {decode_value, Codec, ErrorKind, Prefix, Result, Snapshot, DB};
+
+/// A set of reads and writes to any prefixes of a database that is committed as a whole, or not
+/// at all.
+///
+/// Reads see the database as it was when the transaction was started, together with the writes
+/// of the transaction itself. Nothing is written until `commit` is called, and the commit fails
+/// with `ErrorKind::Conflict` if any key the transaction read or wrote was changed by someone
+/// else since the transaction started. Dropping the transaction or calling `rollback` discards
+/// the writes.
+///
+/// Commits are serialized with each other and with functions like [`Prefix::update_retry`] and
+/// [`Prefix::compare_and_swap`], but not with plain writes like `Prefix::insert`.
+///
+/// See `DB::transaction`
+///
+/// # Examples
+/// ```
+/// # let db = rocksbin::DB::open("db_dir_transaction").unwrap();
+/// let accounts = db.prefix::<String, u64>(b"accounts").unwrap();
+/// accounts.insert("John", &100).unwrap();
+///
+/// let mut tx = db.transaction();
+/// let john = tx.get(&accounts, "John").unwrap().unwrap();
+/// tx.insert(&accounts, "John", &(john - 30)).unwrap();
+/// tx.insert(&accounts, "Lisa", &30).unwrap();
+/// tx.commit().unwrap();
+///
+/// assert_eq!(accounts.get("John").unwrap(), Some(70));
+/// assert_eq!(accounts.get("Lisa").unwrap(), Some(30));
+///
+/// # drop(accounts);
+/// # drop(db);
+/// # std::fs::remove_dir_all("db_dir_transaction").unwrap();
+/// ```
+pub struct Transaction {
+    db: DB,
+    snapshot: Snapshot,
+    /// Pending writes, `None` for removals.
+    writes: BTreeMap<Vec<u8>, Option<Vec<u8>>>,
+    /// Every key that was read or written, checked for conflicts on commit.
+    keys: HashSet<Vec<u8>>,
+}
+
+impl Transaction {
+    pub(crate) fn new(db: DB) -> Transaction {
+        Transaction {
+            snapshot: db.snapshot(),
+            db,
+            writes: BTreeMap::new(),
+            keys: HashSet::new(),
+        }
+    }
+
+    /// Returns the value coresponing to the key in `prefix`, including the writes of this
+    /// transaction.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::get`
+    pub fn get<K, V, C, Q>(&mut self, prefix: &Prefix<K, V, C>, key: &Q) -> Result<Option<V>>
+    where
+        K: Serialize + DeserializeOwned + Borrow<Q>,
+        V: Serialize + DeserializeOwned,
+        C: Codec,
+        Q: Serialize + ?Sized,
+    {
+        let key_buf = prefix.key_buf(key)?;
+        let value = match self.writes.get(&key_buf) {
+            Some(Some(data)) => Some(decode_value::<C, _>(&key_buf, data, prefix.checksum, prefix.values)?),
+            Some(None) => None,
+            None => match self.snapshot.get_raw(&key_buf)? {
+                Some(data) => Some(decode_value::<C, _>(&key_buf, &data, prefix.checksum, prefix.values)?),
+                None => None,
+            },
+        };
+
+        self.keys.insert(key_buf);
+        Ok(value)
+    }
+
+    /// Insert a key-value pair into `prefix` when the transaction is committed.
+    ///
+    /// This function will return `Err` if the key or value could not be serialized.
+    pub fn insert<K, V, C, Q>(&mut self, prefix: &Prefix<K, V, C>, key: &Q, value: &V) -> Result<()>
+    where
+        K: Serialize + DeserializeOwned + Borrow<Q>,
+        V: Serialize + DeserializeOwned,
+        C: Codec,
+        Q: Serialize + ?Sized,
+    {
+        let key_buf = prefix.key_buf(key)?;
+        let value_buf = prefix.value_buf(value)?;
+
+        self.keys.insert(key_buf.clone());
+        self.writes.insert(key_buf, Some(value_buf));
+        Ok(())
+    }
+
+    /// Remove a key-value pair from `prefix` when the transaction is committed.
+    ///
+    /// This function will return `Err` if the key could not be serialized.
+    pub fn remove<K, V, C, Q>(&mut self, prefix: &Prefix<K, V, C>, key: &Q) -> Result<()>
+    where
+        K: Serialize + DeserializeOwned + Borrow<Q>,
+        V: Serialize + DeserializeOwned,
+        C: Codec,
+        Q: Serialize + ?Sized,
+    {
+        let key_buf = prefix.key_buf(key)?;
+
+        self.keys.insert(key_buf.clone());
+        self.writes.insert(key_buf, None);
+        Ok(())
+    }
+
+    /// Apply the writes of the transaction atomically.
+    ///
+    /// This function will return `Err` if:
+    /// - a key the transaction read or wrote was changed since it started, then nothing is written
+    ///   and the error is `ErrorKind::Conflict`
+    /// - some rocksdb error occures
+    pub fn commit(self) -> Result<()> {
+        let _guard = self.db.update_lock.lock().unwrap();
+
+        for key in &self.keys {
+            let current = self.db.db.get(key)?.map(|data| data.to_vec());
+            let started = self.snapshot.get_raw(key)?.map(|data| data.to_vec());
+            if current != started {
+                return Err(Box::new(ErrorKind::Conflict { key: key.clone() }));
+            }
+        }
+
+        let mut batch = rocksdb::WriteBatch::default();
+        for (key, value) in &self.writes {
+            match *value {
+                Some(ref value) => batch.put(key, value)?,
+                None => batch.delete(key)?,
+            }
+        }
+        self.db.db.write(batch)?;
+        Ok(())
+    }
+
+    /// Discard the writes of the transaction.
+    pub fn rollback(self) {}
+}
@@ -0,0 +1,155 @@
+use rocksdb::compaction_filter::Decision;
+use serde::{de::DeserializeOwned, Serialize};
+
+use std::borrow::Borrow;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use {BincodeConfig, Prefix, Result};
+
+/// A prefix where entries can expire.
+///
+/// Every value is stored together with an optional expiry time. Expired entries are treated as
+/// if they do not exist, so `get` returns `Ok(None)` for them.
+///
+/// Expired entries are removed from disk when rocksdb compacts them, e.g., after
+/// `DB::compact_all`. This uses the system clock, not the one set with `with_clock`, and only
+/// happens for prefixes that `DB::ttl_prefix` was called for since the database was opened.
+///
+/// Created with `DB::ttl_prefix`.
+#[derive(Clone)]
+pub struct TtlPrefix<K, V> {
+    prefix: Prefix<K, (Option<u64>, V)>,
+    clock: Arc<dyn Fn() -> SystemTime + Send + Sync>,
+}
+
+impl<K: Serialize + DeserializeOwned, V: Serialize + DeserializeOwned> TtlPrefix<K, V> {
+    pub(crate) fn new(prefix: Prefix<K, (Option<u64>, V)>) -> TtlPrefix<K, V> {
+        TtlPrefix {
+            prefix,
+            clock: Arc::new(SystemTime::now),
+        }
+    }
+
+    /// Use `clock` instead of `SystemTime::now` to get the current time.
+    ///
+    /// This is mostly useful for testing expiry without having to wait.
+    pub fn with_clock<F: Fn() -> SystemTime + Send + Sync + 'static>(mut self, clock: F) -> TtlPrefix<K, V> {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Returns the value coresponing to the key, or `Ok(None)` if there is no such value or it
+    /// has expired.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::get`
+    pub fn get<Q>(&self, key: &Q) -> Result<Option<V>>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let now = self.now();
+        Ok(self.prefix.get(key)?.and_then(|(expires, value)| match expires {
+            Some(expires) if expires <= now => None,
+            _ => Some(value),
+        }))
+    }
+
+    /// Insert a key-value pair that never expires.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::insert`
+    pub fn insert<Q>(&self, key: &Q, value: &V) -> Result<()>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        self.insert_expiring(key, value, None)
+    }
+
+    /// Insert a key-value pair that expires `ttl` from now.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::insert`
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// # let db = rocksbin::DB::open("db_dir_ttl").unwrap();
+    /// let sessions = db.ttl_prefix::<String, u64>(b"sessions").unwrap();
+    ///
+    /// sessions.insert_with_ttl("John", &1234, Duration::from_secs(60)).unwrap();
+    ///
+    /// assert_eq!(sessions.get("John").unwrap(), Some(1234));
+    ///
+    /// # drop(sessions);
+    /// # drop(db);
+    /// # std::fs::remove_dir_all("db_dir_ttl").unwrap();
+    /// ```
+    pub fn insert_with_ttl<Q>(&self, key: &Q, value: &V, ttl: Duration) -> Result<()>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let expires = self.now().saturating_add(millis(ttl));
+        self.insert_expiring(key, value, Some(expires))
+    }
+
+    /// Removes a key-value pair.
+    ///
+    /// This function will return `Err` in the same cases as `Prefix::remove`
+    pub fn remove<Q>(&self, key: &Q) -> Result<()>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        self.prefix.remove(key)
+    }
+
+    fn insert_expiring<Q>(&self, key: &Q, value: &V, expires: Option<u64>) -> Result<()>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let key_buf = self.prefix.key_buf(key)?;
+        // Serializes like `(Option<u64>, V)` without having to clone the value
+        let value_buf = self.prefix.value_buf(&(expires, value))?;
+
+        self.prefix.db.put(&key_buf, &value_buf)?;
+        Ok(())
+    }
+
+    /// Milliseconds since the unix epoch according to the clock.
+    fn now(&self) -> u64 {
+        millis((self.clock)().duration_since(UNIX_EPOCH).unwrap_or_default())
+    }
+}
+
+/// The name of the compaction filter installed on every database.
+pub(crate) const FILTER_NAME: &str = "rocksbin_ttl";
+
+/// The compaction filter installed on every database, it removes the expired entries of the TTL
+/// prefixes in `prefixes`.
+pub(crate) fn compaction_filter(
+    prefixes: Arc<RwLock<Vec<Vec<u8>>>>,
+    config: BincodeConfig,
+) -> impl FnMut(u32, &[u8], &[u8]) -> Decision {
+    move |_level, key, value| {
+        if !prefixes.read().unwrap().iter().any(|prefix| key.starts_with(prefix)) {
+            return Decision::Keep;
+        }
+
+        // The expiry time is serialized first, so the rest of the value can be ignored
+        let now = millis(SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default());
+        match config.deserialize_key::<Option<u64>>(value) {
+            Ok(Some(expires)) if expires <= now => Decision::Remove,
+            _ => Decision::Keep,
+        }
+    }
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration
+        .as_secs()
+        .saturating_mul(1000)
+        .saturating_add(u64::from(duration.subsec_millis()))
+}
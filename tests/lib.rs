@@ -1,8 +1,24 @@
+extern crate bincode;
 extern crate rocksbin;
+extern crate rocksdb;
+extern crate serde;
 extern crate tempfile;
+#[cfg(feature = "async")]
+extern crate tokio;
 extern crate serde_json;
 
-use rocksbin::DB;
+use rocksbin::{
+    Bincode, BincodeConfig, Change, Codec, Either, Entry, ErrorKind, IntEncoding, MergeOperators, Op, DB,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::ops::Bound;
+use std::path::Path;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
 
 #[test]
 fn create_db() {
@@ -237,3 +253,2751 @@ fn import_export() {
     
     assert_eq!(prefix.get("a").unwrap(), Some("b".to_string()));
 }
+
+#[test]
+fn import_export_metadata() {
+    let varint = BincodeConfig {
+        int_encoding: IntEncoding::Varint,
+        ..BincodeConfig::default()
+    };
+    let open = |path: &Path, config| {
+        let mut opts = rocksdb::Options::default();
+        opts.create_if_missing(true);
+        DB::open_with_config(path, opts, config)
+    };
+
+    let dir = tempfile::tempdir().expect("create tempdir #1");
+    let json = {
+        let db = open(dir.path(), varint).expect("open db #1");
+        let prefix = db.checked_prefix::<u64, u64>(b"test").expect("prefix #1");
+        prefix.insert(&1, &2).expect("insert");
+
+        serde_json::to_value(db).expect("serialize")
+    };
+    // Only the pair of the prefix, not the configuration nor the types
+    assert_eq!(json.as_array().expect("array").len(), 1);
+
+    let import_dir = tempfile::tempdir().expect("create tempdir #2");
+    {
+        let db = open(import_dir.path(), varint).expect("open db #2");
+        db.import(json).expect("import");
+
+        let prefix = db.prefix::<u64, u64>(b"test").expect("prefix #2");
+        assert_eq!(prefix.get(&1).expect("get"), Some(2));
+        db.checked_prefix::<u64, String>(b"test").expect("prefix #3");
+    }
+
+    // A configuration in the imported data does not replace the one of the database
+    let empty_dir = tempfile::tempdir().expect("create tempdir #3");
+    let db = open(empty_dir.path(), BincodeConfig::default()).expect("open db #3");
+    let config = format!("[[{:?}, [1, 2, 3]]]", b"__rocksbin_config".to_vec());
+    db.import(&mut serde_json::Deserializer::from_str(&config)).expect("import config");
+    drop(db);
+    open(empty_dir.path(), BincodeConfig::default()).expect("open db #4");
+}
+
+#[test]
+fn count_distinct_by() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u64, u64>(b"test").expect("prefix #1");
+
+    prefix.insert(&1, &10).expect("insert #1");
+    prefix.insert(&2, &20).expect("insert #2");
+    prefix.insert(&3, &10).expect("insert #3");
+    prefix.insert(&4, &30).expect("insert #4");
+    prefix.insert(&5, &20).expect("insert #5");
+
+    assert_eq!(prefix.count_distinct_by(|v| *v).expect("count #1"), 3);
+    assert_eq!(prefix.count_distinct_by(|v| *v > 15).expect("count #2"), 2);
+}
+
+#[test]
+fn prefix_bytes() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let raw_key = {
+        let db = DB::open(dir.path()).expect("open db");
+        let prefix = db.prefix::<u64, u64>(b"test").expect("prefix #1");
+
+        prefix.insert(&5, &7).expect("insert #1");
+        assert_eq!(prefix.get(&5).expect("get #1"), Some(7));
+
+        let mut raw_key = prefix.prefix_bytes().to_vec();
+        raw_key.extend(bincode::serialize(&5u64).unwrap());
+        raw_key
+    };
+
+    let db = rocksdb::DB::open_default(dir.path()).expect("open raw db");
+    let raw_value = db.get(&raw_key).expect("raw get").expect("raw value");
+    assert_eq!(bincode::deserialize::<u64>(&raw_value).unwrap(), 7);
+}
+
+#[test]
+fn changed_between() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u64, u64>(b"test").expect("prefix #1");
+    let other = db.prefix::<u64, u64>(b"test2").expect("prefix #2");
+
+    prefix.insert(&1, &10).expect("insert #1");
+    prefix.insert(&2, &20).expect("insert #2");
+    prefix.insert(&3, &30).expect("insert #3");
+
+    let old = db.snapshot();
+
+    prefix.remove(&1).expect("remove #1");
+    prefix.insert(&2, &21).expect("insert #4");
+    prefix.insert(&4, &40).expect("insert #5");
+    other.insert(&5, &50).expect("insert #6");
+
+    let new = db.snapshot();
+
+    assert_eq!(
+        prefix.changed_between(&old, &new).expect("changed #1"),
+        vec![Change::Remove(1), Change::Update(2, 21), Change::Insert(4, 40)]
+    );
+    assert_eq!(prefix.changed_between(&new, &new).expect("changed #2"), vec![]);
+}
+
+#[test]
+fn insert_with_ttl() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let now = Arc::new(Mutex::new(UNIX_EPOCH + Duration::from_secs(1_000_000)));
+    let clock = now.clone();
+    let prefix = db
+        .ttl_prefix::<u64, u64>(b"test")
+        .expect("prefix #1")
+        .with_clock(move || *clock.lock().unwrap());
+
+    prefix.insert_with_ttl(&5, &7, Duration::from_secs(1)).expect("insert #1");
+    prefix.insert(&6, &8).expect("insert #2");
+    assert_eq!(prefix.get(&5).expect("get #1"), Some(7));
+    assert_eq!(prefix.get(&6).expect("get #2"), Some(8));
+
+    *now.lock().unwrap() += Duration::from_millis(999);
+    assert_eq!(prefix.get(&5).expect("get #3"), Some(7));
+
+    *now.lock().unwrap() += Duration::from_millis(1);
+    assert_eq!(prefix.get(&5).expect("get #4"), None);
+    assert_eq!(prefix.get(&6).expect("get #5"), Some(8));
+}
+
+#[test]
+fn canonical() {
+    let a: HashMap<u64, u64> = (0..100).map(|i| (i, i * 2)).collect();
+    let b: HashMap<u64, u64> = (0..100).rev().map(|i| (i, i * 2)).collect();
+
+    let a_bytes = rocksbin::canonical::serialize(&a).expect("serialize #1");
+    let b_bytes = rocksbin::canonical::serialize(&b).expect("serialize #2");
+    assert_eq!(a_bytes, b_bytes);
+    assert_eq!(bincode::deserialize::<HashMap<u64, u64>>(&a_bytes).unwrap(), a);
+
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db
+        .prefix::<u64, HashMap<u64, u64>>(b"test")
+        .expect("prefix #1")
+        .canonical();
+
+    prefix.insert(&1, &a).expect("insert #1");
+    let old = db.snapshot();
+    prefix.insert(&1, &b).expect("insert #2");
+    let new = db.snapshot();
+
+    assert_eq!(prefix.get(&1).expect("get #1"), Some(b));
+    assert_eq!(prefix.changed_between(&old, &new).expect("changed #1"), vec![]);
+}
+
+#[test]
+fn collect_until_bytes() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u64, u64>(b"test").expect("prefix #1");
+
+    prefix.insert(&5, &7).expect("insert #1");
+    prefix.insert(&6, &8).expect("insert #2");
+    prefix.insert(&7, &9).expect("insert #3");
+
+    // Every value takes up 8 bytes
+    assert_eq!(prefix.collect_until_bytes(20).expect("collect #1"), (vec![(5, 7), (6, 8)], true));
+    assert_eq!(prefix.collect_until_bytes(7).expect("collect #2"), (vec![], true));
+    assert_eq!(
+        prefix.collect_until_bytes(24).expect("collect #3"),
+        (vec![(5, 7), (6, 8), (7, 9)], false)
+    );
+}
+
+#[test]
+fn apply() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u64, u64>(b"test").expect("prefix #1");
+
+    prefix.insert(&5, &7).expect("insert #1");
+    prefix.insert(&6, &8).expect("insert #2");
+
+    prefix
+        .apply(&[
+            Op::Remove(5),
+            Op::Insert(7, 9),
+            Op::Insert(6, 10),
+            Op::Insert(8, 11),
+            Op::Remove(8),
+        ])
+        .expect("apply #1");
+
+    let entries = prefix.iter().collect::<Result<Vec<_>, _>>().expect("iter #1");
+    assert_eq!(entries, vec![(6, 10), (7, 9)]);
+}
+
+#[test]
+fn rekey() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u64, u64>(b"test").expect("prefix #1");
+
+    prefix.insert(&1, &10).expect("insert #1");
+    prefix.insert(&2, &20).expect("insert #2");
+    prefix.insert(&3, &30).expect("insert #3");
+
+    // Every new key except the last collides with an old key
+    assert_eq!(prefix.rekey(|k| k + 1).expect("rekey #1"), 3);
+
+    assert_eq!(prefix.get(&1).expect("get #1"), None);
+    assert_eq!(prefix.get(&2).expect("get #2"), Some(10));
+    assert_eq!(prefix.get(&3).expect("get #3"), Some(20));
+    assert_eq!(prefix.get(&4).expect("get #4"), Some(30));
+    assert_eq!(prefix.iter().count(), 3);
+}
+
+#[test]
+fn get_or_compute() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u64, u64>(b"test").expect("prefix #1").cached();
+    let computed = Cell::new(0);
+    let compute = || {
+        computed.set(computed.get() + 1);
+        7
+    };
+
+    assert_eq!(prefix.get_or_compute(&5, &compute).expect("get #1"), 7);
+    assert_eq!(prefix.get_or_compute(&5, &compute).expect("get #2"), 7);
+    assert_eq!(prefix.get(&5).expect("get #3"), Some(7));
+    assert_eq!(computed.get(), 1);
+
+    prefix.insert(&5, &8).expect("insert #1");
+    assert_eq!(prefix.get_or_compute(&5, &compute).expect("get #4"), 8);
+    assert_eq!(computed.get(), 1);
+
+    prefix.remove(&5).expect("remove #1");
+    assert_eq!(prefix.get(&5).expect("get #5"), None);
+    assert_eq!(prefix.get_or_compute(&5, &compute).expect("get #6"), 7);
+    assert_eq!(computed.get(), 2);
+}
+
+#[test]
+fn cached_race() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u64, u64>(b"test").expect("prefix #1").cached();
+
+    for i in 0..200 {
+        prefix.insert(&i, &i).expect("insert");
+    }
+
+    let reader = {
+        let prefix = prefix.clone();
+        thread::spawn(move || {
+            for _ in 0..5 {
+                for i in 0..200 {
+                    prefix.get(&i).expect("get #1");
+                }
+            }
+        })
+    };
+    for i in 0..200 {
+        prefix.remove(&i).expect("remove");
+    }
+    reader.join().expect("join");
+
+    // No removed value may stay cached
+    for i in 0..200 {
+        assert_eq!(prefix.get(&i).expect("get #2"), None);
+    }
+}
+
+#[test]
+fn partition_by() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u64, u64>(b"test").expect("prefix #1");
+    let even = db.prefix::<u64, u64>(b"even").expect("prefix #2");
+    let odd = db.prefix::<u64, u64>(b"odd").expect("prefix #3");
+
+    for i in 0..9 {
+        prefix.insert(&i, &(i * 10)).expect("insert");
+    }
+
+    assert_eq!(prefix.partition_by(&even, &odd, false, |k| k % 2 == 0).expect("partition #1"), (5, 4));
+
+    assert_eq!(
+        even.iter().collect::<Result<Vec<_>, _>>().expect("iter #1"),
+        vec![(0, 0), (2, 20), (4, 40), (6, 60), (8, 80)]
+    );
+    assert_eq!(
+        odd.iter().collect::<Result<Vec<_>, _>>().expect("iter #2"),
+        vec![(1, 10), (3, 30), (5, 50), (7, 70)]
+    );
+    assert_eq!(prefix.iter().count(), 9);
+
+    // Values are serialized again for destinations that store them differently, and the source
+    // is cleared in the same batches
+    for i in 9..2500 {
+        prefix.insert(&i, &(i * 10)).expect("insert");
+    }
+    let small = db.prefix::<u64, u64>(b"small").expect("prefix #4").checksummed();
+    let large = db.prefix::<u64, u64>(b"large").expect("prefix #5").canonical();
+    assert_eq!(prefix.partition_by(&small, &large, true, |k| *k < 1000).expect("partition #2"), (1000, 1500));
+    assert_eq!(prefix.iter().count(), 0);
+    assert_eq!(small.len().expect("len #1"), 1000);
+    assert_eq!(large.len().expect("len #2"), 1500);
+    assert_eq!(small.get(&999).expect("get #1"), Some(9990));
+    assert_eq!(large.get(&2499).expect("get #2"), Some(24990));
+
+    // Both destinations have to be in the same database as the source
+    let other_dir = tempfile::tempdir().expect("create tempdir");
+    let other_db = DB::open(other_dir.path()).expect("open db #2");
+    let other = other_db.prefix::<u64, u64>(b"other").expect("prefix #6");
+    match *even.partition_by(&other, &odd, true, |_| true).expect_err("partition #3") {
+        ErrorKind::ForeignPrefix => (),
+        ref e => panic!("unexpected error: {:?}", e),
+    }
+    assert_eq!(even.iter().count(), 5);
+    assert!(other.iter().next().is_none());
+}
+
+#[test]
+fn value_size() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u64, (u32, String)>(b"test").expect("prefix #1");
+    let value = (7, "salmon".to_string());
+
+    prefix.insert(&5, &value).expect("insert #1");
+
+    assert_eq!(
+        prefix.value_size(&5).expect("size #1"),
+        Some(bincode::serialized_size(&value).unwrap() as usize)
+    );
+    assert_eq!(prefix.value_size(&6).expect("size #2"), None);
+
+    let checksummed = db.prefix::<u64, (u32, String)>(b"checksummed").expect("prefix #2").checksummed();
+    checksummed.insert(&5, &value).expect("insert #2");
+
+    assert_eq!(
+        checksummed.value_size(&5).expect("size #3"),
+        Some(bincode::serialized_size(&value).unwrap() as usize)
+    );
+}
+
+#[test]
+fn iter_raw_values() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix1 = db.prefix::<u64, String>(b"test").expect("prefix #1");
+    let prefix2 = db.prefix::<u64, String>(b"test2").expect("prefix #2");
+
+    prefix1.insert(&5, &"a".to_string()).expect("insert #1");
+    prefix1.insert(&6, &"b".to_string()).expect("insert #2");
+
+    let entries = prefix1.iter_raw_values().collect::<Result<Vec<_>, _>>().expect("iter #1");
+    assert_eq!(
+        entries,
+        vec![
+            (5, bincode::serialize("a").unwrap()),
+            (6, bincode::serialize("b").unwrap()),
+        ]
+    );
+
+    for (key, value) in entries {
+        prefix2.insert(&key, &bincode::deserialize(&value).unwrap()).expect("insert #3");
+    }
+
+    assert_eq!(prefix2.get(&5).expect("get #1"), Some("a".to_string()));
+    assert_eq!(prefix2.get(&6).expect("get #2"), Some("b".to_string()));
+
+    // The iterator keeps the database alive
+    let iter = prefix1.iter_raw_values();
+    drop(prefix1);
+    drop(prefix2);
+    drop(db);
+    assert_eq!(thread::spawn(move || iter.count()).join().expect("join"), 2);
+}
+
+#[test]
+fn remove_many() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<String, u64>(b"test").expect("prefix #1");
+
+    for i in 0..10 {
+        prefix.insert(&i.to_string(), &i).expect("insert");
+    }
+
+    assert_eq!(prefix.remove_many(vec!["1", "3", "5", "7", "9", "11"]).expect("remove #1"), 6);
+
+    let keys = prefix.keys().collect::<Result<Vec<_>, _>>().expect("keys #1");
+    assert_eq!(keys, vec!["0", "2", "4", "6", "8"]);
+}
+
+#[test]
+fn export_checkpoint() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let export_dir = tempfile::tempdir().expect("create tempdir");
+    let varint = BincodeConfig {
+        int_encoding: IntEncoding::Varint,
+        ..BincodeConfig::default()
+    };
+    let open = |path: &Path, config| {
+        let mut opts = rocksdb::Options::default();
+        opts.create_if_missing(true);
+        DB::open_with_config(path, opts, config)
+    };
+    {
+        let db = open(dir.path(), varint).expect("open db");
+        let prefix1 = db.prefix::<u64, u64>(b"test").expect("prefix #1");
+        let prefix2 = db.prefix::<u64, u64>(b"test2").expect("prefix #2");
+
+        prefix1.insert(&5, &7).expect("insert #1");
+        prefix1.insert(&6, &8).expect("insert #2");
+        prefix2.insert(&5, &9).expect("insert #3");
+
+        for i in 10..2500 {
+            prefix1.insert(&i, &i).expect("insert #4");
+        }
+
+        prefix1.export_checkpoint(export_dir.path().join("export")).expect("export #1");
+
+        // Exporting into an existing database fails without writing to it
+        match *prefix2.export_checkpoint(export_dir.path().join("export")).expect_err("export #2") {
+            ErrorKind::Io(ref e) => assert_eq!(e.kind(), std::io::ErrorKind::AlreadyExists),
+            ref e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    // The export keeps the configuration of the database it was exported from
+    match *open(&export_dir.path().join("export"), BincodeConfig::default()).expect_err("open export #1") {
+        ErrorKind::ConfigMismatch { found, .. } => assert_eq!(found, varint),
+        ref e => panic!("unexpected error: {:?}", e),
+    }
+
+    let db = open(&export_dir.path().join("export"), varint).expect("open export #2");
+    let exported = db.prefix::<u64, u64>(b"").expect("prefix #3");
+    let prefix1 = db.prefix::<u64, u64>(b"test").expect("prefix #4");
+    let prefix2 = db.prefix::<u64, u64>(b"test2").expect("prefix #5");
+
+    let mut entries = exported.iter().collect::<Result<Vec<_>, _>>().expect("iter #1");
+    entries.sort();
+    assert_eq!(entries.len(), 2492);
+    assert_eq!(&entries[..3], &[(5, 7), (6, 8), (10, 10)]);
+    assert_eq!(entries.last(), Some(&(2499, 2499)));
+    assert!(prefix1.iter().next().is_none());
+    assert!(prefix2.iter().next().is_none());
+}
+
+#[test]
+fn update_retry() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u64, u64>(b"test").expect("prefix #1");
+
+    let threads: Vec<_> = (0..4)
+        .map(|_| {
+            let prefix = prefix.clone();
+            thread::spawn(move || {
+                for _ in 0..100 {
+                    prefix
+                        .update_retry(&5, |v| (Some(v.unwrap_or(0) + 1), ()))
+                        .expect("update");
+                }
+            })
+        })
+        .collect();
+
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    assert_eq!(prefix.get(&5).expect("get #1"), Some(400));
+
+    let removed = prefix.update_retry(&5, |v| (None, v)).expect("update #1");
+    assert_eq!(removed, Some(400));
+    assert_eq!(prefix.get(&5).expect("get #2"), None);
+}
+
+#[test]
+fn iter_chunks() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u64, u64>(b"test").expect("prefix #1");
+
+    for i in 0..25 {
+        prefix.insert(&i, &i).expect("insert");
+    }
+
+    let chunks = prefix.iter_chunks(10).collect::<Result<Vec<_>, _>>().expect("chunks #1");
+    assert_eq!(chunks.iter().map(|c| c.len()).collect::<Vec<_>>(), vec![10, 10, 5]);
+    assert_eq!(chunks[2][0], (20, 20));
+}
+
+#[test]
+fn merge_from() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix1 = db.prefix::<u64, u64>(b"test").expect("prefix #1");
+    let prefix2 = db.prefix::<u64, u64>(b"test2").expect("prefix #2");
+
+    prefix1.insert(&1, &10).expect("insert #1");
+    prefix1.insert(&2, &20).expect("insert #2");
+    prefix2.insert(&2, &5).expect("insert #3");
+    prefix2.insert(&3, &7).expect("insert #4");
+
+    assert_eq!(prefix1.merge_from(&prefix2, |a, b| a.unwrap_or(0) + b).expect("merge #1"), 2);
+
+    assert_eq!(
+        prefix1.iter().collect::<Result<Vec<_>, _>>().expect("iter #1"),
+        vec![(1, 10), (2, 25), (3, 7)]
+    );
+    assert_eq!(prefix2.iter().count(), 2);
+}
+
+#[test]
+fn count_distinct_key_prefix() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<(u64, u64), u64>(b"test").expect("prefix #1");
+    let other = db.prefix::<(u64, u64), u64>(b"test2").expect("prefix #2");
+
+    for group in &[1, 2, 3, 255, 256] {
+        for item in 0..20 {
+            prefix.insert(&(*group, item), &item).expect("insert");
+        }
+    }
+    other.insert(&(7, 0), &0).expect("insert #1");
+
+    assert_eq!(prefix.count_distinct_key_prefix::<u64>().expect("count #1"), 5);
+    assert_eq!(other.count_distinct_key_prefix::<u64>().expect("count #2"), 1);
+}
+
+#[test]
+fn set_options() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u64, u64>(b"test").expect("prefix #1");
+
+    db.set_options(&[("write_buffer_size", "8388608"), ("level0_file_num_compaction_trigger", "8")])
+        .expect("set options #1");
+    assert!(db.set_options(&[("max_open_files", "10")]).is_err());
+    assert!(db.set_options(&[("no_such_option", "1")]).is_err());
+
+    prefix.insert(&5, &7).expect("insert #1");
+    assert_eq!(prefix.get(&5).expect("get #1"), Some(7));
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn dump_json() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<(u64, u64), String>(b"test").expect("prefix #1");
+
+    prefix.insert(&(1, 2), &"a".to_string()).expect("insert #1");
+    prefix.insert(&(3, 4), &"b".to_string()).expect("insert #2");
+
+    let mut json = Vec::new();
+    prefix.dump_json(&mut json).expect("dump #1");
+
+    assert_eq!(
+        String::from_utf8(json).unwrap(),
+        r#"[{"key":[1,2],"value":"a"},{"key":[3,4],"value":"b"}]"#
+    );
+}
+
+#[test]
+fn checksummed() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let raw_key = {
+        let db = DB::open(dir.path()).expect("open db");
+        let prefix = db.prefix::<u64, u64>(b"test").expect("prefix #1").checksummed();
+
+        prefix.insert(&5, &7).expect("insert #1");
+        prefix.insert(&6, &8).expect("insert #2");
+        assert_eq!(prefix.get(&5).expect("get #1"), Some(7));
+
+        let mut raw_key = prefix.prefix_bytes().to_vec();
+        raw_key.extend(bincode::serialize(&5u64).unwrap());
+        raw_key
+    };
+
+    {
+        let db = rocksdb::DB::open_default(dir.path()).expect("open raw db");
+        let mut raw_value = db.get(&raw_key).expect("raw get").expect("raw value").to_vec();
+        raw_value[0] ^= 1;
+        db.put(&raw_key, &raw_value).expect("raw put");
+    }
+
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u64, u64>(b"test").expect("prefix #2").checksummed();
+
+    match *prefix.get(&5).unwrap_err() {
+        ErrorKind::Corruption { ref key } => assert_eq!(key, &raw_key),
+        ref e => panic!("unexpected error: {:?}", e),
+    }
+    assert!(prefix.iter().next().unwrap().is_err());
+    assert_eq!(prefix.get(&6).expect("get #2"), Some(8));
+}
+
+#[test]
+fn prewarm() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u64, u64>(b"test").expect("prefix #1");
+
+    for i in 0..100 {
+        prefix.insert(&i, &(i * 2)).expect("insert");
+    }
+
+    prefix.prewarm().expect("prewarm #1");
+
+    assert_eq!(prefix.get(&50).expect("get #1"), Some(100));
+    assert_eq!(prefix.iter().count(), 100);
+}
+
+#[test]
+fn swap() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u64, u64>(b"test").expect("prefix #1");
+
+    let threads: Vec<_> = (0..4)
+        .map(|t| {
+            let prefix = prefix.clone();
+            thread::spawn(move || {
+                (0..100)
+                    .map(|i| prefix.swap(&5, &(t * 100 + i)).expect("swap"))
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect();
+
+    let mut seen: Vec<_> = threads.into_iter().flat_map(|t| t.join().unwrap()).collect();
+    assert_eq!(seen.iter().filter(|v| v.is_none()).count(), 1);
+
+    seen.push(prefix.get(&5).expect("get #1"));
+    let mut seen: Vec<u64> = seen.into_iter().flatten().collect();
+    seen.sort();
+    assert_eq!(seen, (0..400).collect::<Vec<_>>());
+}
+
+#[test]
+fn expire_before() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u8, u64>(b"test").expect("prefix #1");
+    let other = db.prefix::<u8, u64>(b"tess").expect("prefix #2");
+
+    for i in 0..200 {
+        prefix.insert(&i, &u64::from(i)).expect("insert");
+        other.insert(&i, &u64::from(i)).expect("insert");
+    }
+
+    assert_eq!(prefix.expire_before(&150).expect("expire #1"), 150);
+    assert_eq!(prefix.get(&149).expect("get #1"), None);
+    assert_eq!(prefix.get(&150).expect("get #2"), Some(150));
+    assert_eq!(prefix.iter().count(), 50);
+    assert_eq!(other.iter().count(), 200);
+}
+
+#[test]
+fn get_either() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let old = db.prefix::<u64, u32>(b"test").expect("prefix #1");
+    let new = db.prefix::<u64, (u32, u32)>(b"test").expect("prefix #2");
+
+    old.insert(&5, &7).expect("insert #1");
+    new.insert(&6, &(8, 9)).expect("insert #2");
+
+    assert_eq!(new.get_either::<u32, _>(&5).expect("get #1"), Some(Either::Right(7)));
+    assert_eq!(new.get_either::<u32, _>(&6).expect("get #2"), Some(Either::Left((8, 9))));
+    assert_eq!(new.get_either::<u32, _>(&7).expect("get #3"), None);
+}
+
+#[test]
+fn stream_to() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u64, u64>(b"test").expect("prefix #1");
+
+    for i in 0..100 {
+        prefix.insert(&i, &i).expect("insert");
+    }
+
+    let (tx, rx) = mpsc::channel();
+    let producer = prefix.stream_to(tx);
+    let entries = rx.iter().collect::<Result<Vec<_>, _>>().expect("stream #1");
+    producer.join().unwrap();
+    assert_eq!(entries.len(), 100);
+
+    let (tx, rx) = mpsc::channel();
+    let producer = prefix.stream_to(tx);
+    assert!(rx.recv().is_ok());
+    drop(rx);
+    producer.join().unwrap();
+}
+
+#[test]
+fn compact_incremental() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u64, u64>(b"test").expect("prefix #1");
+
+    for i in 0..100 {
+        prefix.insert(&i, &(i * 2)).expect("insert");
+    }
+    for i in 0..50 {
+        prefix.remove(&(i * 2)).expect("remove");
+    }
+
+    prefix.compact_incremental(4).expect("compact #1");
+    prefix.compact_incremental(1000).expect("compact #2");
+
+    assert_eq!(prefix.iter().count(), 50);
+    assert_eq!(prefix.get(&51).expect("get #1"), Some(102));
+    assert_eq!(prefix.get(&50).expect("get #2"), None);
+}
+
+#[test]
+fn partition_get() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u64, u64>(b"test").expect("prefix #1");
+
+    prefix.insert(&5, &7).expect("insert #1");
+    prefix.insert(&6, &8).expect("insert #2");
+
+    let (present, missing) = prefix.partition_get(&[6, 1, 5, 2]).expect("get #1");
+    assert_eq!(present, vec![(6, 8), (5, 7)]);
+    assert_eq!(missing, vec![1, 2]);
+}
+
+#[test]
+fn iter_step() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u8, u64>(b"test").expect("prefix #1");
+    let other = db.prefix::<u8, u64>(b"tesu").expect("prefix #2");
+
+    for i in 0..100 {
+        prefix.insert(&i, &u64::from(i)).expect("insert");
+        other.insert(&i, &u64::from(i)).expect("insert");
+    }
+
+    let sampled = prefix.iter_step(10).collect::<Result<Vec<_>, _>>().expect("iter #1");
+    assert_eq!(sampled.len(), 10);
+    assert_eq!(sampled[0], (0, 0));
+    assert_eq!(sampled[9], (90, 90));
+
+    assert_eq!(prefix.iter_step(1).count(), 100);
+    assert_eq!(prefix.iter_step(30).count(), 4);
+
+    // The iterator keeps the database alive
+    let iter = prefix.iter_step(10);
+    drop(prefix);
+    drop(other);
+    drop(db);
+    assert_eq!(thread::spawn(move || iter.count()).join().expect("join"), 10);
+}
+
+fn open_with_merges<P: AsRef<Path>>(path: P, merges: MergeOperators) -> DB {
+    let mut opts = rocksdb::Options::default();
+    opts.create_if_missing(true);
+    DB::open_with_merge_operators(path, opts, BincodeConfig::default(), merges).expect("open db")
+}
+
+fn max_merges() -> MergeOperators {
+    MergeOperators::new().add::<u64, _>(b"test", |existing, operands| {
+        operands.into_iter().chain(existing).max().unwrap_or(0)
+    })
+}
+
+#[test]
+fn merge_prefix() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = open_with_merges(dir.path(), max_merges());
+    let prefix = db.merge_prefix::<u64, u64>(b"test").expect("prefix #1");
+
+    prefix.insert(&5, &50).expect("insert #1");
+
+    let threads: Vec<_> = (0..4)
+        .map(|t| {
+            let prefix = prefix.clone();
+            thread::spawn(move || {
+                for i in 0..100 {
+                    prefix.merge(&5, &((i * 7 + t * 13) % 300)).expect("merge");
+                    prefix.merge(&6, &(i + t)).expect("merge");
+                }
+            })
+        })
+        .collect();
+
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    assert_eq!(prefix.get(&5).expect("get #1"), Some(299));
+    assert_eq!(prefix.get(&6).expect("get #2"), Some(102));
+
+    // Only registered prefixes with plain values can be merged into
+    match *db.merge_prefix::<u64, u64>(b"other").expect_err("prefix #2") {
+        ErrorKind::MergeNotSupported { .. } => (),
+        ref e => panic!("unexpected error: {:?}", e),
+    }
+    match *db.merge_prefix::<u64, i64>(b"test").expect_err("prefix #3") {
+        ErrorKind::TypeMismatch { .. } => (),
+        ref e => panic!("unexpected error: {:?}", e),
+    }
+    match *prefix.clone().checksummed().merge(&5, &1).expect_err("merge #1") {
+        ErrorKind::MergeNotSupported { .. } => (),
+        ref e => panic!("unexpected error: {:?}", e),
+    }
+    assert!(db.prefix::<u64, u64>(b"other").expect("prefix #4").merge(&5, &1).is_err());
+
+    // The merge functions are registered again when the database is reopened
+    prefix.merge(&7, &3).expect("merge #2");
+    drop(prefix);
+    drop(db);
+
+    let db = open_with_merges(dir.path(), max_merges());
+    db.compact_all();
+    let prefix = db.merge_prefix::<u64, u64>(b"test").expect("prefix #5");
+    assert_eq!(prefix.get(&5).expect("get #3"), Some(299));
+    assert_eq!(prefix.get(&7).expect("get #4"), Some(3));
+}
+
+#[test]
+fn merge_prefix_per_database() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let max_db = open_with_merges(dir.path().join("max"), max_merges());
+    let min_db = open_with_merges(
+        dir.path().join("min"),
+        MergeOperators::new().add::<u64, _>(b"test", |existing, operands| {
+            operands.into_iter().chain(existing).min().unwrap_or(0)
+        }),
+    );
+    let max = max_db.merge_prefix::<u64, u64>(b"test").expect("prefix #1");
+    let min = min_db.merge_prefix::<u64, u64>(b"test").expect("prefix #2");
+
+    for i in 1..10 {
+        max.merge(&1, &i).expect("merge #1");
+        min.merge(&1, &i).expect("merge #2");
+    }
+    max_db.compact_all();
+    min_db.compact_all();
+
+    assert_eq!(max.get(&1).expect("get #1"), Some(9));
+    assert_eq!(min.get(&1).expect("get #2"), Some(1));
+}
+
+#[test]
+fn merge_prefix_with_partial() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let merges = MergeOperators::new().add_with_partial::<Vec<u64>, _, _>(
+        b"test",
+        |existing, operands| {
+            let mut list = existing.unwrap_or_default();
+            list.extend(operands.into_iter().flatten());
+            list
+        },
+        // Only combine small operands ahead of time
+        |operands| {
+            if operands.iter().all(|operand| operand.len() < 10) {
+                Some(operands.into_iter().flatten().collect())
+            } else {
+                None
+            }
+        },
+    );
+    let db = open_with_merges(dir.path(), merges);
+    let prefix = db.merge_prefix::<u64, Vec<u64>>(b"test").expect("prefix #1");
+
+    for i in 0..100 {
+        prefix.merge(&5, &vec![i]).expect("merge #1");
+    }
+    prefix.merge(&5, &(100..120).collect()).expect("merge #2");
+    db.compact_all();
+    prefix.merge(&5, &vec![120]).expect("merge #3");
+
+    assert_eq!(prefix.get(&5).expect("get #1"), Some((0..121).collect()));
+
+    db.compact_all();
+    assert_eq!(prefix.get(&5).expect("get #2"), Some((0..121).collect()));
+}
+
+#[test]
+fn count_by() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u64, (String, u64)>(b"test").expect("prefix #1");
+
+    prefix.insert(&1, &("no".to_string(), 30)).expect("insert #1");
+    prefix.insert(&2, &("se".to_string(), 40)).expect("insert #2");
+    prefix.insert(&3, &("no".to_string(), 50)).expect("insert #3");
+    prefix.insert(&4, &("dk".to_string(), 60)).expect("insert #4");
+
+    let counts = prefix
+        .count_by(|_, (country, _)| country.clone())
+        .expect("count_by #1");
+
+    let mut expected = HashMap::new();
+    expected.insert("no".to_string(), 2);
+    expected.insert("se".to_string(), 1);
+    expected.insert("dk".to_string(), 1);
+    assert_eq!(counts, expected);
+
+    let counts = prefix.count_by(|id, _| id % 2).expect("count_by #2");
+    assert_eq!(counts[&0], 2);
+    assert_eq!(counts[&1], 2);
+}
+
+#[test]
+fn batch() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let group = db.prefix_group(b"group").expect("prefix_group #1");
+    let prefix1 = db.prefix::<u64, String>(b"test").expect("prefix #1");
+    let prefix2 = group.prefix::<String, u64>(b"test").expect("prefix #2");
+
+    prefix1.insert(&1, &"one".to_string()).expect("insert #1");
+
+    let mut batch = db.batch();
+    batch.insert(&prefix1, &2, &"two".to_string()).expect("batch insert #1");
+    batch.insert(&prefix2, "two", &2).expect("batch insert #2");
+    batch.remove(&prefix1, &1).expect("batch remove #1");
+    assert_eq!(batch.len(), 3);
+
+    // Failing before the batch is written leaves everything untouched
+    drop(batch);
+    assert_eq!(prefix1.get(&1).expect("get #1"), Some("one".to_string()));
+    assert_eq!(prefix1.get(&2).expect("get #2"), None);
+    assert_eq!(prefix2.get("two").expect("get #3"), None);
+
+    let mut batch = db.batch();
+    assert!(batch.is_empty());
+    batch.insert(&prefix1, &2, &"two".to_string()).expect("batch insert #3");
+    batch.insert(&prefix2, "two", &2).expect("batch insert #4");
+    batch.remove(&prefix1, &1).expect("batch remove #2");
+    batch.insert(&prefix1, &3, &"three".to_string()).expect("batch insert #5");
+    batch.remove(&prefix1, &3).expect("batch remove #3");
+    batch.remove(&prefix2, "four").expect("batch remove #4");
+    batch.insert(&prefix2, "four", &4).expect("batch insert #6");
+    db.write(batch).expect("write #1");
+
+    assert_eq!(
+        prefix1.iter().collect::<Result<Vec<_>, _>>().expect("iter #1"),
+        vec![(2, "two".to_string())]
+    );
+    assert_eq!(prefix2.get("two").expect("get #4"), Some(2));
+    assert_eq!(prefix2.get("four").expect("get #5"), Some(4));
+}
+
+#[test]
+fn checked_prefix() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    {
+        let db = DB::open(dir.path()).expect("open db #1");
+        let prefix = db.checked_prefix::<u64, String>(b"test").expect("prefix #1");
+        prefix.insert(&1, &"one".to_string()).expect("insert #1");
+    }
+
+    let db = DB::open(dir.path()).expect("open db #2");
+    let prefix = db.checked_prefix::<u64, String>(b"test").expect("prefix #2");
+    assert_eq!(prefix.get(&1).expect("get #1"), Some("one".to_string()));
+
+    match *db.checked_prefix::<u64, u64>(b"test").expect_err("prefix #3") {
+        ErrorKind::TypeMismatch { .. } => (),
+        ref e => panic!("unexpected error: {:?}", e),
+    }
+
+    db.checked_prefix::<u64, u64>(b"test2").expect("prefix #4");
+}
+
+#[test]
+fn ordered() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u64, String>(b"test").expect("prefix #1").ordered();
+
+    for &key in &[70000, 256, 1, 255, 2] {
+        prefix.insert(&key, &key.to_string()).expect("insert");
+    }
+
+    assert_eq!(
+        prefix.keys().collect::<Result<Vec<_>, _>>().expect("keys #1"),
+        vec![1, 2, 255, 256, 70000]
+    );
+    assert_eq!(
+        prefix.values().collect::<Result<Vec<_>, _>>().expect("values #1"),
+        vec!["1", "2", "255", "256", "70000"]
+    );
+    assert_eq!(prefix.get(&256).expect("get #1"), Some("256".to_string()));
+
+    prefix.remove(&256).expect("remove #1");
+    assert_eq!(
+        prefix.iter().map(|e| e.map(|(k, _)| k)).collect::<Result<Vec<_>, _>>().expect("iter #1"),
+        vec![1, 2, 255, 70000]
+    );
+}
+
+#[test]
+fn range() {
+    use std::ops::Bound;
+
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u64, u64>(b"test").expect("prefix #1").ordered();
+    let other = db.prefix::<u64, u64>(b"test2").expect("prefix #2").ordered();
+
+    for i in 0..100 {
+        prefix.insert(&i, &(i * 2)).expect("insert");
+        other.insert(&i, &i).expect("insert other");
+    }
+
+    let keys = |iter: rocksbin::Iter<u64, u64>| {
+        iter.map(|e| e.map(|(k, _)| k))
+            .collect::<Result<Vec<_>, _>>()
+            .expect("collect")
+    };
+
+    let entries = prefix.range(10..20).expect("range #1").collect::<Result<Vec<_>, _>>().expect("collect #1");
+    assert_eq!(entries.len(), 10);
+    assert_eq!(entries[0], (10, 20));
+    assert_eq!(entries[9], (19, 38));
+
+    assert_eq!(keys(prefix.range(10..=12).expect("range #2")), vec![10, 11, 12]);
+    assert_eq!(keys(prefix.range(..3).expect("range #3")), vec![0, 1, 2]);
+    assert_eq!(keys(prefix.range(97..).expect("range #4")), vec![97, 98, 99]);
+    assert_eq!(prefix.range(..).expect("range #5").count(), 100);
+    assert_eq!(
+        keys(prefix.range((Bound::Excluded(5), Bound::Included(7))).expect("range #6")),
+        vec![6, 7]
+    );
+}
+
+#[test]
+fn double_ended() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let before = db.prefix::<u64, u64>(b"tesa").expect("prefix #1").ordered();
+    let prefix = db.prefix::<u64, u64>(b"test").expect("prefix #2").ordered();
+    let after = db.prefix::<u64, u64>(b"tesu").expect("prefix #3").ordered();
+
+    before.insert(&1, &1).expect("insert #1");
+    after.insert(&9, &9).expect("insert #2");
+    for i in 5..8 {
+        prefix.insert(&i, &(i * 10)).expect("insert");
+    }
+
+    assert_eq!(
+        prefix.keys().rev().collect::<Result<Vec<_>, _>>().expect("keys #1"),
+        vec![7, 6, 5]
+    );
+    assert_eq!(
+        prefix.values().rev().collect::<Result<Vec<_>, _>>().expect("values #1"),
+        vec![70, 60, 50]
+    );
+
+    let mut iter = prefix.iter();
+    assert_eq!(iter.next().expect("next #1").expect("next #1"), (5, 50));
+    assert_eq!(iter.next_back().expect("next_back #1").expect("next_back #1"), (7, 70));
+    assert_eq!(iter.next_back().expect("next_back #2").expect("next_back #2"), (6, 60));
+    assert!(iter.next().is_none());
+    assert!(iter.next_back().is_none());
+
+    let mut keys = prefix.keys();
+    assert_eq!(keys.next_back().expect("next_back #3").expect("next_back #3"), 7);
+    assert_eq!(keys.next().expect("next #2").expect("next #2"), 5);
+    assert_eq!(keys.next().expect("next #3").expect("next #3"), 6);
+    assert!(keys.next_back().is_none());
+
+    let entries = prefix.range(5..7).expect("range #1").rev().collect::<Result<Vec<_>, _>>().expect("range #1");
+    assert_eq!(entries, vec![(6, 60), (5, 50)]);
+
+    let empty = db.prefix::<u64, u64>(b"tesb").expect("prefix #4");
+    assert!(empty.keys().next_back().is_none());
+}
+
+#[test]
+fn open_with_options() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+
+    let mut opts = rocksdb::Options::default();
+    assert!(DB::open_with_options(dir.path(), opts).is_err());
+
+    opts = rocksdb::Options::default();
+    opts.create_if_missing(true);
+    opts.set_compression_type(rocksdb::DBCompressionType::Lz4);
+    opts.set_write_buffer_size(1024 * 1024);
+
+    let db = DB::open_with_options(dir.path(), opts).expect("open db");
+    let prefix = db.prefix::<u64, String>(b"test").expect("prefix #1");
+
+    prefix.insert(&1, &"one".to_string()).expect("insert #1");
+    assert_eq!(prefix.get(&1).expect("get #1"), Some("one".to_string()));
+}
+
+#[test]
+fn multi_get() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u64, String>(b"test").expect("prefix #1");
+
+    prefix.insert(&1, &"one".to_string()).expect("insert #1");
+    prefix.insert(&3, &"three".to_string()).expect("insert #2");
+    prefix.insert(&5, &"five".to_string()).expect("insert #3");
+
+    assert_eq!(
+        prefix.multi_get(&[&5, &4, &1, &2, &3]).expect("multi_get #1"),
+        vec![Some("five".to_string()), None, Some("one".to_string()), None, Some("three".to_string())]
+    );
+    assert_eq!(prefix.multi_get::<u64>(&[]).expect("multi_get #2"), vec![]);
+
+    let other = db.prefix::<u64, (u64, u64)>(b"test").expect("prefix #2");
+    assert!(other.multi_get(&[&2, &3]).is_err());
+}
+
+#[test]
+fn clear() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix1 = db.prefix::<u64, u64>(b"test").expect("prefix #1");
+    let prefix2 = db.prefix::<u64, u64>(b"test2").expect("prefix #2");
+    let group = db.prefix_group(b"group").expect("prefix_group #1");
+    let prefix3 = group.prefix::<u64, u64>(b"test").expect("prefix #3");
+
+    for i in 0..10 {
+        prefix1.insert(&i, &i).expect("insert #1");
+        prefix2.insert(&i, &i).expect("insert #2");
+        prefix3.insert(&i, &i).expect("insert #3");
+    }
+
+    prefix1.clear().expect("clear #1");
+    assert_eq!(prefix1.iter().count(), 0);
+    assert_eq!(prefix2.iter().count(), 10);
+    assert_eq!(prefix3.iter().count(), 10);
+
+    prefix2.clear().expect("clear #2");
+    assert_eq!(prefix2.iter().count(), 0);
+    assert_eq!(prefix3.iter().count(), 10);
+}
+
+#[test]
+fn get_or_insert_with() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u64, String>(b"test").expect("prefix #1");
+    let calls = Cell::new(0);
+
+    let value = prefix
+        .get_or_insert_with(&1, || {
+            calls.set(calls.get() + 1);
+            "one".to_string()
+        })
+        .expect("get_or_insert_with #1");
+    assert_eq!(value, "one");
+
+    let value = prefix
+        .get_or_insert_with(&1, || {
+            calls.set(calls.get() + 1);
+            "uno".to_string()
+        })
+        .expect("get_or_insert_with #2");
+    assert_eq!(value, "one");
+    assert_eq!(calls.get(), 1);
+    assert_eq!(prefix.get(&1).expect("get #1"), Some("one".to_string()));
+
+    let calls = Arc::new(Mutex::new(0));
+    let threads: Vec<_> = (0..4)
+        .map(|t| {
+            let prefix = prefix.clone();
+            let calls = calls.clone();
+            thread::spawn(move || {
+                prefix
+                    .get_or_insert_with(&2, || {
+                        *calls.lock().unwrap() += 1;
+                        format!("thread {}", t)
+                    })
+                    .expect("get_or_insert_with #3")
+            })
+        })
+        .collect();
+
+    let values: Vec<_> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+    assert_eq!(*calls.lock().unwrap(), 1);
+    assert!(values.iter().all(|v| *v == values[0]));
+}
+
+#[test]
+fn modify_or_insert() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<String, u64>(b"test").expect("prefix #1");
+
+    prefix.modify("counter", |c| *c += 1).expect("modify #1");
+    assert_eq!(prefix.get("counter").expect("get #1"), None);
+
+    prefix.modify_or_insert("counter", 0, |c| *c += 1).expect("modify_or_insert #1");
+    assert_eq!(prefix.get("counter").expect("get #2"), Some(1));
+
+    prefix.modify_or_insert("counter", 0, |c| *c += 1).expect("modify_or_insert #2");
+    assert_eq!(prefix.get("counter").expect("get #3"), Some(2));
+}
+
+#[test]
+fn insert_get_remove_get() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u64, String>(b"test").expect("prefix #1");
+
+    assert_eq!(prefix.insert_get(&1, &"one".to_string()).expect("insert_get #1"), None);
+    assert_eq!(
+        prefix.insert_get(&1, &"uno".to_string()).expect("insert_get #2"),
+        Some("one".to_string())
+    );
+    assert_eq!(prefix.get(&1).expect("get #1"), Some("uno".to_string()));
+
+    assert_eq!(prefix.remove_get(&1).expect("remove_get #1"), Some("uno".to_string()));
+    assert_eq!(prefix.remove_get(&1).expect("remove_get #2"), None);
+    assert_eq!(prefix.get(&1).expect("get #2"), None);
+}
+
+#[test]
+fn len() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix1 = db.prefix::<u64, u64>(b"test").expect("prefix #1");
+    let prefix2 = db.prefix::<u64, u64>(b"test2").expect("prefix #2");
+
+    assert_eq!(prefix1.len().expect("len #1"), 0);
+    assert!(prefix1.is_empty().expect("is_empty #1"));
+
+    for i in 0..10 {
+        prefix1.insert(&i, &i).expect("insert #1");
+    }
+    prefix2.insert(&1, &1).expect("insert #2");
+
+    assert_eq!(prefix1.len().expect("len #2"), 10);
+    assert!(!prefix1.is_empty().expect("is_empty #2"));
+    assert_eq!(prefix2.len().expect("len #3"), 1);
+}
+
+#[derive(Clone)]
+struct Json;
+
+impl Codec for Json {
+    fn serialize<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, rocksbin::Error> {
+        serde_json::to_vec(value).map_err(|e| Box::new(ErrorKind::Codec(Box::new(e))))
+    }
+
+    fn deserialize<T: DeserializeOwned>(data: &[u8]) -> Result<T, rocksbin::Error> {
+        serde_json::from_slice(data).map_err(|e| Box::new(ErrorKind::Codec(Box::new(e))))
+    }
+}
+
+#[test]
+fn codec() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix1 = db.prefix_with_codec::<u64, (String, Vec<u32>), Bincode>(b"test").expect("prefix #1");
+    let prefix2 = db.prefix_with_codec::<u64, (String, Vec<u32>), Json>(b"test2").expect("prefix #2");
+
+    let value = ("one".to_string(), vec![1, 2, 3]);
+    prefix1.insert(&1, &value).expect("insert #1");
+    prefix2.insert(&1, &value).expect("insert #2");
+
+    assert_eq!(prefix1.get(&1).expect("get #1"), Some(value.clone()));
+    assert_eq!(prefix2.get(&1).expect("get #2"), Some(value.clone()));
+    assert_eq!(prefix2.values().next().expect("values #1").expect("values #1"), value);
+
+    let raw_value = prefix2.iter_raw_values().next().expect("raw #1").expect("raw #1").1;
+    assert_eq!(raw_value, br#"["one",[1,2,3]]"#.to_vec());
+
+    let bincode_view = db.prefix::<u64, (String, Vec<u32>)>(b"test2").expect("prefix #3");
+    assert!(bincode_view.get(&1).is_err());
+}
+
+#[test]
+fn snapshot_prefix() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u64, String>(b"test").expect("prefix #1").ordered();
+
+    prefix.insert(&1, &"one".to_string()).expect("insert #1");
+    prefix.insert(&2, &"two".to_string()).expect("insert #2");
+
+    let snapshot = db.snapshot();
+    let old = snapshot.view(db.prefix::<u64, String>(b"test").expect("prefix #2").ordered());
+
+    prefix.insert(&3, &"three".to_string()).expect("insert #3");
+    prefix.remove(&1).expect("remove #1");
+
+    assert_eq!(old.get(&1).expect("get #1"), Some("one".to_string()));
+    assert_eq!(old.get(&3).expect("get #2"), None);
+    assert!(!old.contains_key(&3).expect("contains_key #1"));
+    assert_eq!(old.keys().collect::<Result<Vec<_>, _>>().expect("keys #1"), vec![1, 2]);
+    assert_eq!(old.keys().rev().collect::<Result<Vec<_>, _>>().expect("keys #2"), vec![2, 1]);
+    assert_eq!(prefix.keys().collect::<Result<Vec<_>, _>>().expect("keys #3"), vec![2, 3]);
+
+    // The snapshot keeps the database alive
+    drop(prefix);
+    drop(db);
+    let unordered = snapshot.prefix::<u64, String>(b"test").expect("prefix #3");
+    assert_eq!(unordered.values().count(), 2);
+    assert_eq!(old.iter().count(), 2);
+}
+
+#[test]
+fn contains_key() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    // Implements neither `Serialize` nor `Deserialize`
+    struct Opaque;
+
+    let prefix1 = db.prefix::<u64, u8>(b"test").expect("prefix #1");
+    let prefix2 = db.prefix::<u64, Opaque>(b"test").expect("prefix #2");
+
+    prefix1.insert(&1, &1).expect("insert #1");
+
+    assert!(prefix2.contains_key(&1).expect("contains_key #1"));
+    assert!(!prefix2.contains_key(&2).expect("contains_key #2"));
+}
+
+#[test]
+fn destroy_repair() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let path = dir.path().join("db");
+    {
+        let db = DB::open(&path).expect("open db #1");
+        let prefix = db.prefix::<u64, u64>(b"test").expect("prefix #1");
+        prefix.insert(&1, &1).expect("insert #1");
+    }
+
+    DB::repair(&path, rocksdb::Options::default()).expect("repair #1");
+    {
+        let db = DB::open(&path).expect("open db #2");
+        let prefix = db.prefix::<u64, u64>(b"test").expect("prefix #2");
+        assert_eq!(prefix.get(&1).expect("get #1"), Some(1));
+    }
+
+    DB::destroy(&path, rocksdb::Options::default()).expect("destroy #1");
+    let files: Vec<_> = std::fs::read_dir(&path)
+        .map(|entries| entries.map(|e| e.expect("read_dir").file_name()).collect())
+        .unwrap_or_default();
+    assert!(files.iter().all(|f| !f.to_string_lossy().ends_with(".sst")));
+    assert!(!path.join("CURRENT").exists());
+}
+
+#[test]
+fn compare_and_swap() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<String, u64>(b"test").expect("prefix #1");
+
+    prefix.insert("lock", &0).expect("insert #1");
+
+    let threads: Vec<_> = (1..3)
+        .map(|t| {
+            let prefix = prefix.clone();
+            thread::spawn(move || prefix.compare_and_swap("lock", Some(&0), Some(&t)).expect("compare_and_swap"))
+        })
+        .collect();
+
+    let swapped: Vec<_> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+    assert_eq!(swapped.iter().filter(|&&s| s).count(), 1);
+
+    let winner = if swapped[0] { 1 } else { 2 };
+    assert_eq!(prefix.get("lock").expect("get #1"), Some(winner));
+
+    assert!(!prefix.compare_and_swap("missing", Some(&0), Some(&1)).expect("compare_and_swap #1"));
+    assert!(prefix.compare_and_swap("missing", None, Some(&1)).expect("compare_and_swap #2"));
+    assert!(prefix.compare_and_swap("missing", Some(&1), None).expect("compare_and_swap #3"));
+    assert_eq!(prefix.get("missing").expect("get #2"), None);
+}
+
+#[test]
+fn counter_prefix() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = open_with_merges(dir.path(), MergeOperators::new().counter(b"test"));
+    let prefix = db.counter_prefix::<String>(b"test").expect("prefix #1");
+
+    prefix.insert("visits", &10).expect("insert #1");
+
+    let threads: Vec<_> = (0..8)
+        .map(|_| {
+            let prefix = prefix.clone();
+            thread::spawn(move || {
+                for _ in 0..250 {
+                    prefix.merge("visits", &1).expect("merge");
+                }
+                prefix.merge("other", &-1).expect("merge");
+            })
+        })
+        .collect();
+
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    assert_eq!(prefix.get("visits").expect("get #1"), Some(2010));
+    assert_eq!(prefix.get("other").expect("get #2"), Some(-8));
+
+    // Another database using the same prefix for something else does not replace the counter
+    let other_dir = tempfile::tempdir().expect("create tempdir");
+    let other_db = open_with_merges(other_dir.path(), max_merges());
+    match *other_db.counter_prefix::<String>(b"test").expect_err("prefix #2") {
+        ErrorKind::TypeMismatch { .. } => (),
+        ref e => panic!("unexpected error: {:?}", e),
+    }
+    match *other_db.counter_prefix::<String>(b"visits").expect_err("prefix #3") {
+        ErrorKind::MergeNotSupported { .. } => (),
+        ref e => panic!("unexpected error: {:?}", e),
+    }
+
+    prefix.merge("visits", &5).expect("merge #1");
+    db.compact_all();
+    assert_eq!(prefix.get("visits").expect("get #3"), Some(2015));
+}
+
+#[test]
+fn sync_and_compact() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    {
+        let db = DB::open(dir.path()).expect("open db #1");
+        let prefix = db.prefix::<u64, String>(b"test").expect("prefix #1");
+
+        prefix.insert_sync(&1, &"one".to_string()).expect("insert_sync #1");
+        prefix.insert(&2, &"two".to_string()).expect("insert #1");
+        prefix.remove(&2).expect("remove #1");
+
+        let mut batch = db.batch();
+        batch.insert(&prefix, &3, &"three".to_string()).expect("batch insert #1");
+        db.write_sync(batch).expect("write_sync #1");
+
+        db.compact_all();
+    }
+
+    let db = DB::open(dir.path()).expect("open db #2");
+    let prefix = db.prefix::<u64, String>(b"test").expect("prefix #2");
+    assert_eq!(
+        prefix.iter().collect::<Result<Vec<_>, _>>().expect("iter #1"),
+        vec![(1, "one".to_string()), (3, "three".to_string())]
+    );
+}
+
+#[test]
+fn checkpoint() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path().join("db")).expect("open db #1");
+    let prefix = db.prefix::<u64, String>(b"test").expect("prefix #1");
+
+    prefix.insert(&1, &"one".to_string()).expect("insert #1");
+    prefix.insert(&2, &"two".to_string()).expect("insert #2");
+
+    db.checkpoint(dir.path().join("copy")).expect("checkpoint #1");
+    assert!(db.checkpoint(dir.path().join("copy")).is_err());
+
+    prefix.insert(&3, &"three".to_string()).expect("insert #3");
+
+    let copy = DB::open(dir.path().join("copy")).expect("open db #2");
+    let copied = copy.prefix::<u64, String>(b"test").expect("prefix #2");
+    assert_eq!(
+        copied.iter().collect::<Result<Vec<_>, _>>().expect("iter #1"),
+        vec![(1, "one".to_string()), (2, "two".to_string())]
+    );
+}
+
+#[test]
+fn error_accessors() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path().join("db")).expect("open db");
+    let prefix1 = db.prefix::<u64, u8>(b"test").expect("prefix #1");
+    let prefix2 = db.prefix::<u64, String>(b"test").expect("prefix #2");
+
+    prefix1.insert(&1, &1).expect("insert #1");
+
+    let err = prefix2.get(&1).expect_err("get #1");
+    assert!(err.is_bincode());
+    assert!(!err.is_rocksdb());
+    assert!(err.as_bincode().is_some());
+    assert!(err.as_rocksdb().is_none());
+
+    std::fs::write(dir.path().join("file"), b"not a database").expect("write file");
+    let err = DB::open(dir.path().join("file")).expect_err("open file");
+    assert!(err.is_rocksdb());
+    assert!(!err.is_bincode());
+    assert!(err.as_rocksdb().is_some());
+}
+
+#[test]
+fn first_last() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u64, String>(b"test").expect("prefix #1").ordered();
+    let other = db.prefix::<u64, String>(b"test2").expect("prefix #2").ordered();
+
+    assert_eq!(prefix.first().expect("first #1"), None);
+    assert_eq!(prefix.last().expect("last #1"), None);
+
+    other.insert(&1, &"other".to_string()).expect("insert #1");
+    for &i in &[6, 5, 7] {
+        prefix.insert(&i, &i.to_string()).expect("insert");
+    }
+
+    assert_eq!(prefix.first().expect("first #2"), Some((5, "5".to_string())));
+    assert_eq!(prefix.last().expect("last #2"), Some((7, "7".to_string())));
+}
+
+#[test]
+fn iter_from() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u64, u64>(b"test").expect("prefix #1").ordered();
+    let other = db.prefix::<u64, u64>(b"test2").expect("prefix #2").ordered();
+
+    for i in 1..10 {
+        prefix.insert(&i, &(i * 10)).expect("insert");
+        other.insert(&i, &i).expect("insert other");
+    }
+
+    let mut iter = prefix.iter_from(&5).expect("iter_from #1");
+    assert_eq!(iter.next().expect("next #1").expect("next #1"), (5, 50));
+    assert_eq!(iter.count(), 4);
+
+    assert_eq!(prefix.iter_from(&0).expect("iter_from #2").count(), 9);
+    assert_eq!(prefix.iter_from(&10).expect("iter_from #3").count(), 0);
+}
+
+#[test]
+fn extend() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u64, String>(b"test").expect("prefix #1");
+
+    prefix.extend((0..1000).map(|i| (i, i.to_string()))).expect("extend #1");
+
+    assert_eq!(prefix.len().expect("len #1"), 1000);
+    for &i in &[0, 17, 256, 511, 999] {
+        assert_eq!(prefix.get(&i).expect("get"), Some(i.to_string()));
+    }
+    assert_eq!(prefix.get(&1000).expect("get #1"), None);
+}
+
+#[test]
+fn retain() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u64, u64>(b"test").expect("prefix #1").ordered();
+
+    for i in 1..10 {
+        prefix.insert(&i, &(i * 3)).expect("insert");
+    }
+
+    prefix.retain(|_, v| v % 2 == 0).expect("retain #1");
+    assert_eq!(
+        prefix.keys().collect::<Result<Vec<_>, _>>().expect("keys #1"),
+        vec![2, 4, 6, 8]
+    );
+
+    prefix.retain(|k, _| *k > 4).expect("retain #2");
+    assert_eq!(
+        prefix.iter().collect::<Result<Vec<_>, _>>().expect("iter #1"),
+        vec![(6, 18), (8, 24)]
+    );
+
+    // A value made even by update_retry while retaining is never removed
+    for i in 100..1100 {
+        prefix.insert(&i, &1).expect("insert");
+    }
+    let updater = {
+        let prefix = prefix.clone();
+        thread::spawn(move || {
+            (100..1100)
+                .filter(|i| {
+                    prefix
+                        .update_retry(i, |v| match v {
+                            Some(v) => (Some(v + 1), true),
+                            None => (None, false),
+                        })
+                        .expect("update")
+                })
+                .collect::<Vec<_>>()
+        })
+    };
+    prefix.retain(|_, v| v % 2 == 0).expect("retain #3");
+
+    for i in updater.join().expect("join") {
+        assert_eq!(prefix.get(&i).expect("get"), Some(2));
+    }
+}
+
+#[test]
+fn iter_ref() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<String, (u64, String)>(b"test").expect("prefix #1").checksummed();
+    let other = db.prefix::<String, (u64, String)>(b"test2").expect("prefix #2");
+
+    for i in 0..100u64 {
+        prefix.insert(&format!("key {}", i), &(i, format!("value {}", i))).expect("insert");
+    }
+    other.insert("other", &(0, "other".to_string())).expect("insert other");
+
+    let expected = prefix.iter().collect::<Result<Vec<_>, _>>().expect("iter #1");
+
+    let mut found = Vec::new();
+    let mut iter = prefix.iter_ref();
+    while let Some(entry) = iter.next_ref::<&str, (u64, &str)>() {
+        let (key, (n, value)) = entry.expect("next_ref");
+        found.push((key.to_string(), (n, value.to_string())));
+    }
+    assert_eq!(found, expected);
+    assert!(iter.next_ref::<&str, (u64, &str)>().is_none());
+}
+
+#[test]
+fn scan_prefix() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<(u32, u32), u32>(b"test").expect("prefix #1").ordered();
+
+    for user in 0..5 {
+        for time in 0..(user + 1) {
+            prefix.insert(&(user, time * 100), &(user * time)).expect("insert");
+        }
+    }
+    prefix.insert(&(255, 0), &0).expect("insert #1");
+    prefix.insert(&(256, 0), &0).expect("insert #2");
+
+    let rows = prefix.scan_prefix(&3u32).expect("scan_prefix #1").collect::<Result<Vec<_>, _>>().expect("scan #1");
+    assert_eq!(rows, vec![((3, 0), 0), ((3, 100), 3), ((3, 200), 6), ((3, 300), 9)]);
+
+    assert_eq!(prefix.scan_prefix(&255u32).expect("scan_prefix #2").count(), 1);
+    assert_eq!(prefix.scan_prefix(&10u32).expect("scan_prefix #3").count(), 0);
+    assert_eq!(prefix.scan_prefix(&3u32).expect("scan_prefix #4").next_back().expect("rev").expect("rev").0, (3, 300));
+}
+
+#[test]
+fn raw() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u32, u32>(b"test").expect("prefix");
+
+    prefix.insert(&1, &10).expect("insert");
+
+    let mut key = b"\x04\0\0\0test".to_vec();
+    key.extend(bincode::serialize(&1u32).expect("serialize key"));
+    let value = db.raw().get(&key).expect("raw get #1").expect("raw get #2");
+    assert_eq!(bincode::deserialize::<u32>(&value).expect("deserialize"), 10);
+
+    db.raw().put(&key, &bincode::serialize(&20u32).expect("serialize value")).expect("raw put");
+    assert_eq!(prefix.get(&1).expect("get"), Some(20));
+}
+
+#[test]
+fn cf_prefix() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db #1");
+    let users = db.cf_prefix::<u32, String>("users").expect("cf_prefix #1");
+    let posts = db.cf_prefix::<u32, String>("posts").expect("cf_prefix #2");
+
+    users.insert(&1, &"John".to_string()).expect("insert #1");
+    users.insert(&2, &"Lisa".to_string()).expect("insert #2");
+    posts.insert(&1, &"Hello".to_string()).expect("insert #3");
+
+    let all_users = users.iter().collect::<Result<Vec<_>, _>>().expect("iter #1");
+    assert_eq!(all_users, vec![(1, "John".to_string()), (2, "Lisa".to_string())]);
+    assert_eq!(posts.keys().collect::<Result<Vec<_>, _>>().expect("keys"), vec![1]);
+    assert_eq!(users.get(&1).expect("get #1"), Some("John".to_string()));
+
+    posts.remove(&1).expect("remove");
+    assert!(!posts.contains_key(&1).expect("contains_key"));
+
+    drop(users);
+    drop(posts);
+    drop(db);
+
+    let db = DB::open(dir.path()).expect("open db #2");
+    let users = db.cf_prefix::<u32, String>("users").expect("cf_prefix #3");
+    assert_eq!(users.values().count(), 2);
+    assert_eq!(db.prefix::<u32, String>(b"users").expect("prefix").iter().count(), 0);
+}
+
+#[test]
+fn short_raw_keys() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u32, u32>(b"test").expect("prefix");
+
+    prefix.insert(&1, &10).expect("insert #1");
+    prefix.insert(&2, &20).expect("insert #2");
+
+    // Keys shorter than the serialized prefix, sorting both before and after it
+    db.raw().put(&[4, 0, 0, 0, b't'], b"").expect("raw put #1");
+    db.raw().put(&[4, 0, 0, 0, b'u'], b"").expect("raw put #2");
+    db.raw().put(&[5], b"").expect("raw put #3");
+
+    assert_eq!(prefix.iter().count(), 2);
+    assert_eq!(prefix.iter().rev().count(), 2);
+    assert_eq!(prefix.keys().collect::<Result<Vec<_>, _>>().expect("keys"), vec![1, 2]);
+    assert_eq!(prefix.values().rev().collect::<Result<Vec<_>, _>>().expect("values"), vec![20, 10]);
+    assert_eq!(prefix.len().expect("len"), 2);
+    prefix.clear().expect("clear");
+    assert!(prefix.is_empty().expect("is_empty"));
+}
+
+#[test]
+fn prefix_boundary() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let first = db.prefix::<u32, u32>(b"a").expect("prefix #1").ordered();
+    let second = db.prefix::<String, String>(b"b").expect("prefix #2");
+
+    for i in 0..10 {
+        first.insert(&i, &i).expect("insert #1");
+        second.insert(&i.to_string(), &i.to_string()).expect("insert #2");
+    }
+
+    let mut iter = first.iter();
+    for i in 0..10 {
+        assert_eq!(iter.next().expect("next").expect("entry"), (i, i));
+    }
+    assert!(iter.next().is_none());
+    assert!(iter.next().is_none());
+
+    let mut keys = first.keys();
+    assert_eq!(keys.by_ref().last().expect("last").expect("key"), 9);
+    assert!(keys.next().is_none());
+
+    let mut values = second.values().rev();
+    assert_eq!(values.by_ref().count(), 10);
+    assert!(values.next().is_none());
+}
+
+#[test]
+fn ttl_compaction() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.ttl_prefix::<u64, u64>(b"test").expect("prefix #1");
+    let raw = db.prefix::<u64, (Option<u64>, u64)>(b"test").expect("prefix #2");
+
+    prefix.insert_with_ttl(&5, &7, Duration::from_millis(10)).expect("insert #1");
+    prefix.insert_with_ttl(&6, &8, Duration::from_secs(3600)).expect("insert #2");
+    prefix.insert(&7, &9).expect("insert #3");
+    assert_eq!(raw.len().expect("len #1"), 3);
+
+    // Expiry times have millisecond granularity
+    thread::sleep(Duration::from_millis(50));
+    db.compact_all();
+
+    assert_eq!(raw.keys().collect::<Result<Vec<_>, _>>().expect("keys"), vec![6, 7]);
+    assert_eq!(prefix.get(&6).expect("get #1"), Some(8));
+    assert_eq!(prefix.get(&7).expect("get #2"), Some(9));
+}
+
+#[test]
+fn transaction() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u32, u32>(b"test").expect("prefix");
+
+    prefix.insert(&1, &10).expect("insert");
+
+    let mut first = db.transaction();
+    let mut second = db.transaction();
+    first.insert(&prefix, &1, &11).expect("insert #1");
+    first.insert(&prefix, &2, &20).expect("insert #2");
+    assert_eq!(first.get(&prefix, &2).expect("get #1"), Some(20));
+    assert_eq!(prefix.get(&2).expect("get #2"), None);
+    second.insert(&prefix, &1, &12).expect("insert #3");
+
+    first.commit().expect("commit #1");
+    let err = second.commit().expect_err("commit #2");
+    match *err {
+        ErrorKind::Conflict { .. } => (),
+        _ => panic!("expected a conflict, found {}", err),
+    }
+    assert_eq!(prefix.get(&1).expect("get #3"), Some(11));
+    assert_eq!(prefix.get(&2).expect("get #4"), Some(20));
+
+    let mut third = db.transaction();
+    third.remove(&prefix, &1).expect("remove");
+    assert_eq!(third.get(&prefix, &1).expect("get #5"), None);
+    third.rollback();
+    assert_eq!(prefix.get(&1).expect("get #6"), Some(11));
+}
+
+#[test]
+fn modify_all() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u32, u32>(b"test").expect("prefix #1");
+    let other = db.prefix::<u32, u32>(b"test2").expect("prefix #2");
+
+    for i in 0..100 {
+        prefix.insert(&i, &i).expect("insert #1");
+        other.insert(&i, &i).expect("insert #2");
+    }
+
+    prefix.modify_all(|_, value| *value *= 2).expect("modify_all #1");
+    for i in 0..100 {
+        assert_eq!(prefix.get(&i).expect("get #1"), Some(i * 2));
+        assert_eq!(other.get(&i).expect("get #2"), Some(i));
+    }
+
+    prefix.modify_all(|key, value| if *key == 3 { *value = 0 }).expect("modify_all #2");
+    assert_eq!(prefix.get(&3).expect("get #3"), Some(0));
+    assert_eq!(prefix.get(&4).expect("get #4"), Some(8));
+
+    // Increments made by update_retry while modifying are not lost
+    for i in 100..1100 {
+        prefix.insert(&i, &0).expect("insert #3");
+    }
+    let updater = {
+        let prefix = prefix.clone();
+        thread::spawn(move || {
+            for i in 100..1100 {
+                prefix.update_retry(&i, |v| (v.map(|v| v + 1), ())).expect("update");
+            }
+        })
+    };
+    prefix.modify_all(|_, value| *value += 1000).expect("modify_all #3");
+    updater.join().expect("join");
+
+    for i in 100..1100 {
+        assert_eq!(prefix.get(&i).expect("get #5"), Some(1001));
+    }
+}
+
+#[test]
+fn large_values() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u32, (String, Vec<u8>, Vec<(u64, String)>)>(b"test").expect("prefix");
+
+    let blob = (
+        "blob".to_string(),
+        (0..4 * 1024 * 1024).map(|i| (i % 251) as u8).collect::<Vec<_>>(),
+        (0..1000).map(|i| (i, i.to_string())).collect::<Vec<_>>(),
+    );
+    prefix.insert(&1, &blob).expect("insert");
+
+    assert_eq!(prefix.get(&1).expect("get"), Some(blob));
+}
+
+#[test]
+fn stored_size() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let big = db.prefix::<u32, String>(b"big").expect("prefix #1");
+    let small = db.prefix::<u32, String>(b"small").expect("prefix #2");
+
+    for i in 0..1000 {
+        big.insert(&i, &"x".repeat(100)).expect("insert #1");
+    }
+    small.insert(&1, &"x".to_string()).expect("insert #2");
+
+    assert!(big.stored_size().expect("size #1") > 100_000);
+    assert!(big.stored_size().expect("size #2") > small.stored_size().expect("size #3"));
+    // 9 bytes of prefix, 4 of key and 9 of value
+    assert_eq!(small.stored_size().expect("size #4"), 22);
+
+    db.compact_all();
+    assert!(db.live_files_size().expect("live_files_size") > 0);
+}
+
+#[test]
+fn many_prefixes() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefixes = (0..20)
+        .map(|i| db.prefix::<u32, u32>(format!("prefix{}", i).as_bytes()).expect("prefix"))
+        .collect::<Vec<_>>();
+
+    for (i, prefix) in prefixes.iter().enumerate() {
+        for j in 0..500 {
+            prefix.insert(&j, &(i as u32)).expect("insert");
+        }
+    }
+    db.compact_all();
+
+    for (i, prefix) in prefixes.iter().enumerate() {
+        let values = prefix.values().collect::<Result<Vec<_>, _>>().expect("values");
+        assert_eq!(values.len(), 500);
+        assert!(values.iter().all(|&value| value == i as u32));
+        assert_eq!(prefix.keys().rev().count(), 500);
+    }
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn import_json() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<(u32, String), Vec<u64>>(b"test").expect("prefix");
+
+    for i in 0..100 {
+        prefix.insert(&(i, i.to_string()), &vec![i as u64; i as usize]).expect("insert");
+    }
+    let entries = prefix.iter().collect::<Result<Vec<_>, _>>().expect("iter #1");
+
+    let mut json = Vec::new();
+    prefix.dump_json(&mut json).expect("dump");
+    prefix.clear().expect("clear");
+    assert!(prefix.is_empty().expect("is_empty"));
+
+    prefix.import_json(&json[..]).expect("import #1");
+    assert_eq!(prefix.iter().collect::<Result<Vec<_>, _>>().expect("iter #2"), entries);
+
+    prefix.import_json(&b"[{\"key\": [1000, \"a\"]}]"[..]).expect_err("import #2");
+    prefix.import_json(&b"[] []"[..]).expect_err("import #3");
+    assert_eq!(prefix.len().expect("len"), 100);
+}
+
+#[test]
+fn send_iterators() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u32, u32>(b"test").expect("prefix").ordered();
+
+    for i in 0..100 {
+        prefix.insert(&i, &(i * 2)).expect("insert");
+    }
+
+    let keys = prefix.keys();
+    let values = prefix.values();
+    let iter = db.snapshot().view(prefix.clone()).iter();
+    drop(prefix);
+    drop(db);
+
+    let handle = thread::spawn(move || {
+        let keys = keys.collect::<Result<Vec<_>, _>>().expect("keys");
+        let values = values.collect::<Result<Vec<_>, _>>().expect("values");
+        (keys, values, iter.count())
+    });
+
+    let (keys, values, count) = handle.join().expect("join");
+    assert_eq!(keys, (0..100).collect::<Vec<_>>());
+    assert_eq!(values, (0..100).map(|i| i * 2).collect::<Vec<_>>());
+    assert_eq!(count, 100);
+}
+
+#[test]
+fn remove_if() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u32, String>(b"test").expect("prefix");
+
+    for round in 0..50 {
+        prefix.insert(&1, &format!("job {}", round)).expect("insert");
+
+        let handles = (0..2)
+            .map(|_| {
+                let prefix = prefix.clone();
+                thread::spawn(move || prefix.remove_if(&1, &format!("job {}", round)).expect("remove_if #1"))
+            })
+            .collect::<Vec<_>>();
+        let removed = handles.into_iter().map(|handle| handle.join().expect("join")).filter(|&removed| removed).count();
+
+        assert_eq!(removed, 1);
+        assert_eq!(prefix.get(&1).expect("get"), None);
+    }
+
+    prefix.insert(&2, &"job".to_string()).expect("insert");
+    assert!(!prefix.remove_if(&2, &"other job".to_string()).expect("remove_if #2"));
+    assert!(prefix.contains_key(&2).expect("contains_key"));
+}
+
+#[test]
+fn index() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let users = db.prefix::<u32, (String, String)>(b"users").expect("prefix #1");
+    let emails = db.prefix::<String, u32>(b"emails").expect("prefix #2");
+    let users = users.indexed(emails.clone(), |_, user| user.1.clone()).expect("indexed #1");
+
+    users.insert(&1, &("John".to_string(), "john@example.com".to_string())).expect("insert #1");
+    users.insert(&2, &("Lisa".to_string(), "lisa@example.com".to_string())).expect("insert #2");
+    assert_eq!(users.get("john@example.com").expect("get #1"), Some(1));
+    assert_eq!(users.get("lisa@example.com").expect("get #2"), Some(2));
+
+    users.insert(&1, &("John".to_string(), "john@example.org".to_string())).expect("insert #3");
+    assert_eq!(users.get("john@example.com").expect("get #3"), None);
+    assert_eq!(users.get("john@example.org").expect("get #4"), Some(1));
+
+    users.remove(&2).expect("remove #1");
+    assert_eq!(users.get("lisa@example.com").expect("get #5"), None);
+    assert_eq!(users.prefix().get(&2).expect("get #6"), None);
+    assert_eq!(emails.len().expect("len #1"), 1);
+
+    // An index key taken over by another key stays when the old key is removed
+    users.insert(&3, &("Johnny".to_string(), "john@example.org".to_string())).expect("insert #4");
+    users.remove(&1).expect("remove #2");
+    assert_eq!(users.get("john@example.org").expect("get #7"), Some(3));
+    assert_eq!(emails.len().expect("len #2"), 1);
+
+    // The index has to be in the same database, as both are written in one batch
+    let other_dir = tempfile::tempdir().expect("create tempdir");
+    let other_db = DB::open(other_dir.path()).expect("open db #2");
+    let other_emails = other_db.prefix::<String, u32>(b"emails").expect("prefix #3");
+    let err = users
+        .prefix()
+        .clone()
+        .indexed(other_emails, |_, user| user.1.clone())
+        .map(|_| ())
+        .expect_err("indexed #2");
+    match *err {
+        ErrorKind::ForeignPrefix => (),
+        ref e => panic!("unexpected error: {:?}", e),
+    }
+}
+
+#[test]
+fn prefix_conflict() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+
+    let _prefix = db.prefix::<u32, u32>(b"test").expect("prefix #1");
+    let err = db.prefix_group(b"test").map(|_| ()).expect_err("prefix_group #1");
+    match *err {
+        ErrorKind::PrefixConflict { ref prefix } => assert_eq!(prefix, b"test"),
+        _ => panic!("expected a prefix conflict, found {}", err),
+    }
+
+    let group = db.prefix_group(b"group").expect("prefix_group #2");
+    db.prefix::<u32, u32>(b"group").map(|_| ()).expect_err("prefix #2");
+
+    let _sub_prefix = group.prefix::<u32, u32>(b"sub").expect("prefix #3");
+    group.prefix_group(b"sub").map(|_| ()).expect_err("prefix_group #3");
+
+    db.prefix::<u32, u32>(b"test").expect("prefix #4");
+    db.prefix_group(b"group").expect("prefix_group #4");
+}
+
+#[test]
+fn raw_prefix() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.raw_prefix::<u32>(b"test").expect("prefix");
+    let payload = [0x0a, 0x03, b'f', b'o', b'o', 0x10, 0x01];
+
+    prefix.insert(&1, &payload).expect("insert #1");
+    assert_eq!(prefix.get(&1).expect("get #1"), Some(payload.to_vec()));
+    assert!(prefix.contains_key(&1).expect("contains_key"));
+
+    let mut key = b"\x04\0\0\0test".to_vec();
+    key.extend(bincode::serialize(&1u32).expect("serialize key"));
+    assert_eq!(&db.raw().get(&key).expect("raw get #1").expect("raw get #2")[..], &payload[..]);
+
+    let entries = prefix.iter().collect::<Result<Vec<_>, _>>().expect("iter");
+    assert_eq!(entries, vec![(1, payload.to_vec())]);
+
+    prefix.remove(&1).expect("remove");
+    assert_eq!(prefix.get(&1).expect("get #2"), None);
+
+    // The iterator keeps the database alive
+    prefix.insert(&2, &payload).expect("insert #2");
+    let iter = prefix.iter();
+    drop(prefix);
+    drop(db);
+    assert_eq!(thread::spawn(move || iter.count()).join().expect("join"), 1);
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn async_prefix() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u32, String>(b"test").expect("prefix").into_async();
+    let runtime = tokio::runtime::Runtime::new().expect("runtime");
+
+    runtime.block_on(prefix.insert(1, "one".to_string())).expect("insert");
+    assert_eq!(runtime.block_on(prefix.get(1)).expect("get #1"), Some("one".to_string()));
+    assert!(runtime.block_on(prefix.contains_key(1)).expect("contains_key"));
+
+    runtime.block_on(prefix.remove(1)).expect("remove");
+    assert_eq!(runtime.block_on(prefix.get(1)).expect("get #2"), None);
+    assert_eq!(prefix.prefix().len().expect("len"), 0);
+}
+
+#[test]
+fn to_vec() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u32, String>(b"test").expect("prefix #1");
+
+    assert_eq!(prefix.to_vec().expect("to_vec #1"), vec![]);
+
+    for i in 0..100 {
+        prefix.insert(&i, &i.to_string()).expect("insert #1");
+    }
+
+    let mut entries = Vec::new();
+    for entry in prefix.iter() {
+        entries.push(entry.expect("entry"));
+    }
+    assert_eq!(prefix.to_vec().expect("to_vec #2"), entries);
+    assert_eq!(prefix.keys_vec().expect("keys_vec"), entries.iter().map(|e| e.0).collect::<Vec<_>>());
+    assert_eq!(prefix.values_vec().expect("values_vec"), entries.into_iter().map(|e| e.1).collect::<Vec<_>>());
+
+    let broken = db.prefix::<u32, (u64, u64)>(b"test").expect("prefix #2");
+    broken.to_vec().expect_err("to_vec #3");
+    broken.values_vec().expect_err("values_vec #2");
+}
+
+#[test]
+fn value_schema_change() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let two = db.prefix::<u32, (u32, u32)>(b"test").expect("prefix #1");
+    let three = db.prefix::<u32, (u32, u32, u32)>(b"test").expect("prefix #2");
+
+    two.insert(&1, &(1, 2)).expect("insert #1");
+    three.insert(&2, &(1, 2, 3)).expect("insert #2");
+
+    let err = three.get(&1).expect_err("get #1");
+    assert!(err.is_bincode(), "unexpected error {}", err);
+    let err = two.get(&2).expect_err("get #2");
+    assert!(err.is_bincode(), "unexpected error {}", err);
+
+    assert_eq!(three.get(&3).expect("get #3"), None);
+    assert_eq!(two.get(&1).expect("get #4"), Some((1, 2)));
+}
+
+#[test]
+fn split_ranges() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u32, u32>(b"test").expect("prefix");
+
+    assert_eq!(prefix.split_ranges(4).expect("split #1").len(), 1);
+
+    for i in 0..1000 {
+        prefix.insert(&i, &(i * 2)).expect("insert");
+    }
+
+    let ranges = prefix.split_ranges(4).expect("split #2");
+    assert_eq!(ranges.len(), 4);
+
+    let handles = ranges
+        .into_iter()
+        .map(|range| {
+            let prefix = prefix.clone();
+            thread::spawn(move || prefix.range(range).expect("range").collect::<Result<Vec<_>, _>>().expect("iter"))
+        })
+        .collect::<Vec<_>>();
+    let parts = handles.into_iter().map(|handle| handle.join().expect("join")).collect::<Vec<_>>();
+
+    assert!(parts.iter().all(|part| part.len() == 250));
+    let all = parts.into_iter().flatten().collect::<Vec<_>>();
+    assert_eq!(all, prefix.iter().collect::<Result<Vec<_>, _>>().expect("iter"));
+
+    assert_eq!(prefix.split_ranges(2000).expect("split #3").len(), 1000);
+    assert_eq!(prefix.split_ranges(0).expect("split #4").len(), 1);
+}
+
+#[test]
+fn write_batch() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let users = db.prefix::<u32, (String, u8)>(b"users").expect("prefix #1");
+    let sessions = db.prefix::<String, u32>(b"sessions").expect("prefix #2");
+
+    sessions.insert("old", &1).expect("insert");
+
+    db.write_batch()
+        .put(&users, &1, &("John".to_string(), 30))
+        .put(&sessions, "new", &1)
+        .delete(&sessions, "old")
+        .commit()
+        .expect("commit #1");
+
+    assert_eq!(users.get(&1).expect("get #1"), Some(("John".to_string(), 30)));
+    assert_eq!(sessions.keys().collect::<Result<Vec<_>, _>>().expect("keys"), vec!["new".to_string()]);
+
+    db.write_batch()
+        .put(&users, &2, &("Lisa".to_string(), 25))
+        .delete(&users, &1)
+        .delete(&sessions, "new")
+        .commit()
+        .expect("commit #2");
+
+    assert_eq!(users.get(&2).expect("get #2"), Some(("Lisa".to_string(), 25)));
+    assert_eq!(sessions.len().expect("len #1"), 0);
+    assert_eq!(users.len().expect("len #2"), 1);
+}
+
+#[test]
+fn cf_prefix_types() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    {
+        let db = DB::open(dir.path()).expect("open db #1");
+        let prefix = db.cf_prefix::<u64, String>("test").expect("cf_prefix #1");
+        prefix.insert(&1, &"one".to_string()).expect("insert #1");
+    }
+
+    let db = DB::open(dir.path()).expect("open db #2");
+    let prefix = db.cf_prefix::<u64, String>("test").expect("cf_prefix #2");
+    assert_eq!(prefix.get(&1).expect("get #1"), Some("one".to_string()));
+
+    match *db.cf_prefix::<u64, u64>("test").err().expect("cf_prefix #3") {
+        ErrorKind::TypeMismatch { .. } => (),
+        ref e => panic!("unexpected error: {:?}", e),
+    }
+
+    // Column families and key prefixes with the same name are checked separately
+    db.checked_prefix::<u64, u64>(b"test").expect("prefix #1");
+    db.cf_prefix::<u64, u64>("test2").expect("cf_prefix #4");
+}
+
+#[test]
+fn cf_prefix_with_options() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db #1");
+
+    let mut lookup_table_opts = rocksdb::BlockBasedOptions::default();
+    lookup_table_opts.set_bloom_filter(10, false);
+    lookup_table_opts.set_lru_cache(1024 * 1024);
+    let mut lookup_opts = rocksdb::Options::default();
+    lookup_opts.set_block_based_table_factory(&lookup_table_opts);
+
+    let mut scan_table_opts = rocksdb::BlockBasedOptions::default();
+    scan_table_opts.disable_cache();
+    let mut scan_opts = rocksdb::Options::default();
+    scan_opts.set_block_based_table_factory(&scan_table_opts);
+
+    let lookup = db.cf_prefix_with_options::<u32, String>("lookup", lookup_opts).expect("cf_prefix #1");
+    let scan = db.cf_prefix_with_options::<u32, u64>("scan", scan_opts).expect("cf_prefix #2");
+
+    for i in 0..100 {
+        lookup.insert(&i, &i.to_string()).expect("insert #1");
+        scan.insert(&i, &(i as u64 * 2)).expect("insert #2");
+    }
+
+    assert_eq!(lookup.get(&42).expect("get #1"), Some("42".to_string()));
+    assert_eq!(lookup.get(&100).expect("get #2"), None);
+    assert_eq!(scan.values().map(|v| v.expect("values")).sum::<u64>(), 9900);
+
+    drop(lookup);
+    drop(scan);
+    drop(db);
+
+    let db = DB::open(dir.path()).expect("open db #2");
+    let lookup = db.cf_prefix::<u32, String>("lookup").expect("cf_prefix #3");
+    let scan = db.cf_prefix::<u32, u64>("scan").expect("cf_prefix #4");
+    assert_eq!(lookup.get(&7).expect("get #3"), Some("7".to_string()));
+    assert_eq!(scan.keys().count(), 100);
+}
+
+#[test]
+fn debug() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let group = db.prefix_group(b"group").expect("prefix_group");
+    let prefix = group.prefix::<u32, String>(b"users").expect("prefix");
+
+    let prefix_debug = format!("{:?}", prefix);
+    assert!(prefix_debug.contains(r#"b"\x05\0\0\0group\x05\0\0\0users""#), "{}", prefix_debug);
+    assert!(prefix_debug.contains("u32"));
+    assert!(prefix_debug.contains("String"));
+
+    assert_eq!(format!("{:?}", group), r#"PrefixGroup { prefix: b"\x05\0\0\0group" }"#);
+    assert!(format!("{:?}", db).contains(&format!("{:?}", dir.path())));
+}
+
+#[test]
+fn path() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db #1");
+    assert_eq!(db.path(), dir.path());
+    drop(db);
+
+    let mut opts = rocksdb::Options::default();
+    opts.create_if_missing(true);
+    let db = DB::open_with_options(dir.path().join("sibling"), opts).expect("open db #2");
+    assert_eq!(db.path(), dir.path().join("sibling"));
+}
+
+#[test]
+fn sized_iter() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u32, u32>(b"test").expect("prefix #1");
+    let other = db.prefix::<u32, u32>(b"other").expect("prefix #2");
+
+    for i in 0..50 {
+        prefix.insert(&i, &i).expect("insert #1");
+        other.insert(&i, &i).expect("insert #2");
+    }
+
+    let mut iter = prefix.sized_iter();
+    assert_eq!(iter.len(), 50);
+    assert_eq!(iter.size_hint(), (50, Some(50)));
+
+    // Writes made after the iterator was created are not visited
+    prefix.insert(&100, &100).expect("insert #3");
+    prefix.remove(&0).expect("remove");
+
+    let mut count = 0;
+    while let Some(item) = iter.next() {
+        item.expect("item");
+        count += 1;
+        assert_eq!(iter.len(), 50 - count);
+        if count == 10 {
+            iter.next_back().expect("next_back").expect("item");
+            count += 1;
+        }
+    }
+    assert_eq!(count, 50);
+    assert_eq!(iter.size_hint(), (0, Some(0)));
+
+    let all = prefix.sized_iter().collect::<Result<Vec<_>, _>>().expect("collect");
+    assert_eq!(all.len(), 50);
+    assert_eq!(prefix.sized_iter().len(), 50);
+    assert_eq!(db.prefix::<u32, u32>(b"empty").expect("prefix #3").sized_iter().len(), 0);
+}
+
+#[test]
+fn count_range() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u32, u32>(b"test").expect("prefix #1").ordered();
+    let next = db.prefix::<u32, u32>(b"tesu").expect("prefix #2").ordered();
+
+    for i in 0..100 {
+        prefix.insert(&i, &i).expect("insert #1");
+        next.insert(&i, &i).expect("insert #2");
+    }
+
+    assert_eq!(prefix.count_range(10..50).expect("count_range #1"), 40);
+    assert_eq!(prefix.count_range(10..=50).expect("count_range #2"), 41);
+    assert_eq!(prefix.count_range(90..).expect("count_range #3"), 10);
+    assert_eq!(prefix.count_range(..).expect("count_range #4"), 100);
+    assert_eq!(prefix.count_range((Bound::Excluded(10), Bound::Excluded(20))).expect("count_range #5"), 9);
+    assert_eq!(prefix.count_range(200..300).expect("count_range #6"), 0);
+}
+
+#[test]
+fn migrate_values() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let old = db.prefix::<u64, u32>(b"old").expect("prefix #1");
+    let new = db.prefix::<u64, String>(b"new").expect("prefix #2");
+
+    for i in 0..2500 {
+        old.insert(&i, &(i as u32 * 3)).expect("insert");
+    }
+
+    old.migrate_values(&new, |n| n.to_string()).expect("migrate_values");
+
+    assert_eq!(new.len().expect("len #1"), 2500);
+    assert_eq!(new.get(&0).expect("get #1"), Some("0".to_string()));
+    assert_eq!(new.get(&2499).expect("get #2"), Some("7497".to_string()));
+    for item in new.iter() {
+        let (key, value) = item.expect("iter");
+        assert_eq!(value, (key * 3).to_string());
+    }
+    assert_eq!(old.len().expect("len #2"), 2500);
+}
+
+#[test]
+fn drain() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u32, String>(b"test").expect("prefix #1").ordered();
+    let other = db.prefix::<u32, String>(b"other").expect("prefix #2");
+
+    for i in 0..10 {
+        prefix.insert(&i, &i.to_string()).expect("insert #1");
+        other.insert(&i, &i.to_string()).expect("insert #2");
+    }
+
+    let drained = prefix.drain_range(2..5).expect("drain_range");
+    assert_eq!(drained, vec![(2, "2".to_string()), (3, "3".to_string()), (4, "4".to_string())]);
+    assert_eq!(prefix.len().expect("len #1"), 7);
+
+    let drained = prefix.drain().expect("drain #1");
+    assert_eq!(drained.iter().map(|&(k, _)| k).collect::<Vec<_>>(), vec![0, 1, 5, 6, 7, 8, 9]);
+    assert_eq!(drained[2], (5, "5".to_string()));
+    assert_eq!(prefix.len().expect("len #2"), 0);
+    assert_eq!(prefix.drain().expect("drain #2"), vec![]);
+
+    assert_eq!(other.len().expect("len #3"), 10);
+}
+
+#[test]
+fn stats() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u32, u32>(b"test").expect("prefix");
+
+    assert_eq!(db.stats().expect("stats #1").num_keys, Some(0));
+
+    for i in 0..100 {
+        prefix.insert(&i, &i).expect("insert");
+    }
+    db.compact_all();
+
+    let stats = db.stats().expect("stats #2");
+    assert_eq!(stats.num_keys, Some(100));
+    assert!(stats.sst_files.expect("sst_files") > 0);
+    assert_eq!(stats.sst_size, Some(db.live_files_size().expect("live_files_size")));
+}
+
+#[test]
+fn ingest_sorted() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u64, String>(b"test").expect("prefix").ordered();
+
+    prefix.ingest_sorted((0..10_000).map(|i| (i, i.to_string()))).expect("ingest_sorted #1");
+
+    assert_eq!(prefix.len().expect("len #1"), 10_000);
+    for &i in &[0, 17, 4242, 9999] {
+        assert_eq!(prefix.get(&i).expect("get #1"), Some(i.to_string()));
+    }
+    assert_eq!(prefix.get(&10_000).expect("get #2"), None);
+
+    let result = prefix.ingest_sorted(vec![(20_000, "a".to_string()), (20_001, "b".to_string()), (10_500, "c".to_string())]);
+    match *result.expect_err("ingest_sorted #2") {
+        ErrorKind::Unsorted { .. } => (),
+        ref e => panic!("unexpected error: {:?}", e),
+    }
+    assert_eq!(prefix.get(&10_500).expect("get #3"), None);
+
+    let result = prefix.ingest_sorted(vec![(30_000, "a".to_string()), (30_000, "b".to_string())]);
+    assert!(result.is_err());
+}
+
+#[test]
+fn prefix_group_clear() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let group = db.prefix_group(b"group").expect("prefix_group #1");
+    let sibling = db.prefix_group(b"groups").expect("prefix_group #2");
+    let users = group.prefix::<u32, String>(b"users").expect("prefix #1");
+    let posts = group.prefix::<u32, u32>(b"posts").expect("prefix #2");
+    let nested = group.prefix_group(b"nested").expect("prefix_group #3").prefix::<u32, u32>(b"a").expect("prefix #3");
+    let sibling_prefix = sibling.prefix::<u32, u32>(b"users").expect("prefix #4");
+    let top = db.prefix::<u32, u32>(b"group").expect("prefix #5");
+
+    assert!(!group.contains_any().expect("contains_any #1"));
+    assert_eq!(group.prefix_names().expect("prefix_names #1"), Vec::<Vec<u8>>::new());
+
+    for i in 0..10 {
+        users.insert(&i, &i.to_string()).expect("insert #1");
+        posts.insert(&i, &i).expect("insert #2");
+        nested.insert(&i, &i).expect("insert #3");
+        sibling_prefix.insert(&i, &i).expect("insert #4");
+        top.insert(&i, &i).expect("insert #5");
+    }
+
+    assert!(group.contains_any().expect("contains_any #2"));
+    assert_eq!(
+        group.prefix_names().expect("prefix_names #2"),
+        vec![b"posts".to_vec(), b"users".to_vec(), b"nested".to_vec()]
+    );
+
+    group.clear().expect("clear");
+
+    assert!(!group.contains_any().expect("contains_any #3"));
+    assert!(users.is_empty().expect("is_empty #1"));
+    assert!(posts.is_empty().expect("is_empty #2"));
+    assert!(nested.is_empty().expect("is_empty #3"));
+    assert_eq!(sibling_prefix.len().expect("len #1"), 10);
+    assert_eq!(top.len().expect("len #2"), 10);
+}
+
+#[test]
+fn bincode_config() {
+    let varint = BincodeConfig {
+        int_encoding: IntEncoding::Varint,
+        ..BincodeConfig::default()
+    };
+    let open = |path: &std::path::Path, config| {
+        let mut opts = rocksdb::Options::default();
+        opts.create_if_missing(true);
+        DB::open_with_config(path, opts, config)
+    };
+
+    let fixint_dir = tempfile::tempdir().expect("create tempdir #1");
+    let varint_dir = tempfile::tempdir().expect("create tempdir #2");
+    let fixint_db = open(fixint_dir.path(), BincodeConfig::default()).expect("open db #1");
+    let varint_db = open(varint_dir.path(), varint).expect("open db #2");
+
+    let fixint_prefix = fixint_db.prefix::<u64, (u64, String)>(b"test").expect("prefix #1");
+    let varint_prefix = varint_db.prefix::<u64, (u64, String)>(b"test").expect("prefix #2");
+    for i in 0..100 {
+        fixint_prefix.insert(&i, &(i * 2, i.to_string())).expect("insert #1");
+        varint_prefix.insert(&i, &(i * 2, i.to_string())).expect("insert #2");
+    }
+
+    let entries = varint_prefix.iter().collect::<Result<Vec<_>, _>>().expect("iter");
+    assert_eq!(entries.len(), 100);
+    assert_eq!(varint_prefix.get(&42).expect("get #1"), Some((84, "42".to_string())));
+
+    let raw_size = |db: &DB| {
+        db.raw()
+            .iterator(rocksdb::IteratorMode::Start)
+            .map(|(k, v)| k.len() + v.len())
+            .sum::<usize>()
+    };
+    assert!(raw_size(&varint_db) < raw_size(&fixint_db));
+
+    drop(varint_prefix);
+    drop(varint_db);
+    drop(fixint_prefix);
+    drop(fixint_db);
+
+    // The configuration of a database can not be changed
+    match *open(varint_dir.path(), BincodeConfig::default()).expect_err("open db #3") {
+        ErrorKind::ConfigMismatch { expected, found } => {
+            assert_eq!(expected, BincodeConfig::default());
+            assert_eq!(found, varint);
+        }
+        ref e => panic!("unexpected error: {:?}", e),
+    }
+    assert!(open(fixint_dir.path(), varint).is_err());
+
+    let varint_db = open(varint_dir.path(), varint).expect("open db #4");
+    let varint_prefix = varint_db.prefix::<u64, (u64, String)>(b"test").expect("prefix #3");
+    assert_eq!(varint_prefix.get(&99).expect("get #2"), Some((198, "99".to_string())));
+}
+
+#[test]
+fn get_many() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let users = db.prefix::<u64, (String, u8)>(b"users").expect("prefix #1");
+    let settings = db.prefix::<String, bool>(b"settings").expect("prefix #2").checksummed();
+    let sessions = db.prefix::<String, u64>(b"sessions").expect("prefix #3").ordered();
+
+    users.insert(&1, &("John".to_string(), 30)).expect("insert #1");
+    settings.insert("dark_mode", &true).expect("insert #2");
+    sessions.insert("abc", &1).expect("insert #3");
+
+    let mut reads = db.multi_get();
+    let user = reads.add(&users, &1).expect("add #1");
+    let dark_mode = reads.add(&settings, "dark_mode").expect("add #2");
+    let session = reads.add(&sessions, "abc").expect("add #3");
+    let missing = reads.add(&users, &2).expect("add #4");
+    assert_eq!(reads.len(), 4);
+
+    let values = db.get_many(reads).expect("get_many #1");
+
+    // Writes after the read are not seen
+    users.insert(&1, &("Lisa".to_string(), 25)).expect("insert #4");
+
+    assert_eq!(values.get(&user).expect("get #1"), Some(("John".to_string(), 30)));
+    assert_eq!(values.get(&dark_mode).expect("get #2"), Some(true));
+    assert_eq!(values.get(&session).expect("get #3"), Some(1));
+    assert_eq!(values.get(&missing).expect("get #4"), None);
+
+    // Keys of other reads are rejected, even if their index is in range
+    let mut other_reads = db.multi_get();
+    let other_user = other_reads.add(&users, &1).expect("add #5");
+    let other_session = other_reads.add(&sessions, "abc").expect("add #6");
+    let other_values = db.get_many(other_reads).expect("get_many #2");
+    assert_eq!(other_values.get(&other_user).expect("get #5"), Some(("Lisa".to_string(), 25)));
+    match *values.get(&other_session).expect_err("get #6") {
+        ErrorKind::ForeignMultiGetKey => (),
+        ref e => panic!("unexpected error: {:?}", e),
+    }
+    match *other_values.get(&missing).expect_err("get #7") {
+        ErrorKind::ForeignMultiGetKey => (),
+        ref e => panic!("unexpected error: {:?}", e),
+    }
+}
+
+#[test]
+fn compact_prefix() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u64, Vec<u8>>(b"test").expect("prefix #1");
+    let other = db.prefix::<u64, u64>(b"other").expect("prefix #2");
+
+    for i in 0..10_000 {
+        prefix.insert(&i, &vec![i as u8; 100]).expect("insert #1");
+    }
+    for i in 0..100 {
+        other.insert(&i, &i).expect("insert #2");
+    }
+    db.compact_all();
+    let size = db.live_files_size().expect("live_files_size #1");
+
+    prefix.clear().expect("clear");
+    prefix.compact().expect("compact");
+
+    assert!(db.live_files_size().expect("live_files_size #2") < size);
+    assert_eq!(prefix.iter().count(), 0);
+    assert_eq!(other.len().expect("len"), 100);
+}
+
+#[test]
+fn find() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<u32, (String, u32)>(b"test").expect("prefix").ordered();
+
+    prefix.insert(&1, &("John".to_string(), 30)).expect("insert #1");
+    prefix.insert(&2, &("Lisa".to_string(), 25)).expect("insert #2");
+    prefix.insert(&3, &("Bob".to_string(), 41)).expect("insert #3");
+    prefix.insert(&4, &("Eve".to_string(), 25)).expect("insert #4");
+
+    assert_eq!(prefix.find(|user| user.0 == "Bob").expect("find #1"), Some((3, ("Bob".to_string(), 41))));
+    assert_eq!(prefix.find(|user| user.0 == "Alice").expect("find #2"), None);
+
+    // The scan stops at the first match
+    let visited = Cell::new(0);
+    let found = prefix
+        .find(|user| {
+            visited.set(visited.get() + 1);
+            user.1 == 25
+        })
+        .expect("find #3");
+    assert_eq!(found, Some((2, ("Lisa".to_string(), 25))));
+    assert_eq!(visited.get(), 2);
+
+    let found = prefix.find_all(|user| user.1 == 25).expect("find_all #1");
+    assert_eq!(found, vec![(2, ("Lisa".to_string(), 25)), (4, ("Eve".to_string(), 25))]);
+    assert_eq!(prefix.find_all(|user| user.1 > 100).expect("find_all #2"), vec![]);
+}
+
+#[test]
+fn size_limits() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db").max_key_size(16).max_value_size(64);
+    let prefix = db.prefix::<String, Vec<u8>>(b"test").expect("prefix #1");
+
+    prefix.insert("key", &vec![1; 32]).expect("insert #1");
+
+    match *prefix.insert("key", &vec![1; 100]).expect_err("insert #2") {
+        ErrorKind::ValueTooLarge { size, limit } => {
+            assert_eq!(size, 108);
+            assert_eq!(limit, 64);
+        }
+        ref e => panic!("unexpected error: {:?}", e),
+    }
+    assert_eq!(prefix.get("key").expect("get #1"), Some(vec![1; 32]));
+
+    let long_key = "k".repeat(100);
+    match *prefix.insert(&long_key, &vec![1]).expect_err("insert #3") {
+        ErrorKind::KeyTooLarge { size, limit } => {
+            assert_eq!(size, 108);
+            assert_eq!(limit, 16);
+        }
+        ref e => panic!("unexpected error: {:?}", e),
+    }
+
+    // Batches check the limits before anything is written
+    let mut batch = db.batch();
+    batch.insert(&prefix, "other", &vec![2]).expect("batch insert #1");
+    assert!(batch.insert(&prefix, "other", &vec![2; 100]).is_err());
+
+    // Groups share the limits of their database
+    let grouped = db.prefix_group(b"group").expect("prefix_group").prefix::<u32, Vec<u8>>(b"test").expect("prefix #2");
+    assert!(grouped.insert(&1, &vec![3; 100]).is_err());
+
+    // So do column family and raw prefixes
+    let cf = db.cf_prefix::<String, Vec<u8>>("test").expect("cf_prefix");
+    cf.insert("key", &vec![4; 32]).expect("cf insert #1");
+    match *cf.insert(&long_key, &vec![4]).expect_err("cf insert #2") {
+        ErrorKind::KeyTooLarge { .. } => (),
+        ref e => panic!("unexpected error: {:?}", e),
+    }
+    match *cf.insert("key", &vec![4; 100]).expect_err("cf insert #3") {
+        ErrorKind::ValueTooLarge { .. } => (),
+        ref e => panic!("unexpected error: {:?}", e),
+    }
+    assert!(cf.get(&long_key).is_err());
+
+    let raw = db.raw_prefix::<String>(b"raw").expect("raw_prefix");
+    raw.insert("key", &[5; 64]).expect("raw insert #1");
+    match *raw.insert("key", &[5; 65]).expect_err("raw insert #2") {
+        ErrorKind::ValueTooLarge { size, limit } => {
+            assert_eq!(size, 65);
+            assert_eq!(limit, 64);
+        }
+        ref e => panic!("unexpected error: {:?}", e),
+    }
+    assert_eq!(raw.get("key").expect("raw get"), Some(vec![5; 64]));
+}
+
+#[test]
+fn entry() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db = DB::open(dir.path()).expect("open db");
+    let prefix = db.prefix::<String, Vec<u32>>(b"test").expect("prefix");
+
+    // or_insert
+    assert_eq!(prefix.entry("a").expect("entry #1").or_insert(vec![1]).expect("or_insert #1"), vec![1]);
+    assert_eq!(prefix.entry("a").expect("entry #2").or_insert(vec![2]).expect("or_insert #2"), vec![1]);
+    assert_eq!(prefix.get("a").expect("get #1"), Some(vec![1]));
+
+    // or_insert_with is only called for vacant entries
+    let value = prefix
+        .entry("a")
+        .expect("entry #3")
+        .or_insert_with(|| panic!("entry is occupied"))
+        .expect("or_insert_with #1");
+    assert_eq!(value, vec![1]);
+    let value = prefix.entry("b").expect("entry #4").or_insert_with(|| vec![3, 4]).expect("or_insert_with #2");
+    assert_eq!(value, vec![3, 4]);
+    assert_eq!(prefix.get("b").expect("get #2"), Some(vec![3, 4]));
+
+    // and_modify writes the modified value back
+    let value = prefix
+        .entry("b")
+        .expect("entry #5")
+        .and_modify(|v| v.push(5))
+        .expect("and_modify #1")
+        .or_insert(vec![])
+        .expect("or_insert #3");
+    assert_eq!(value, vec![3, 4, 5]);
+    assert_eq!(prefix.get("b").expect("get #3"), Some(vec![3, 4, 5]));
+
+    prefix
+        .entry("c")
+        .expect("entry #6")
+        .and_modify(|_| panic!("entry is vacant"))
+        .expect("and_modify #2");
+    assert_eq!(prefix.get("c").expect("get #4"), None);
+
+    // or_default
+    assert_eq!(prefix.entry("c").expect("entry #7").or_default().expect("or_default #1"), vec![]);
+    assert_eq!(prefix.get("c").expect("get #5"), Some(vec![]));
+    assert_eq!(prefix.entry("a").expect("entry #8").or_default().expect("or_default #2"), vec![1]);
+
+    // Occupied and vacant entries
+    match prefix.entry("a").expect("entry #9") {
+        Entry::Occupied(mut entry) => {
+            assert_eq!(entry.get(), &vec![1]);
+            assert_eq!(entry.insert(vec![6]).expect("insert"), vec![1]);
+            assert_eq!(prefix.get("a").expect("get #6"), Some(vec![6]));
+            assert_eq!(entry.remove().expect("remove"), vec![6]);
+        }
+        Entry::Vacant(_) => panic!("entry is vacant"),
+    }
+    assert_eq!(prefix.get("a").expect("get #7"), None);
+}